@@ -0,0 +1,180 @@
+//! Async driver on top of `embedded-hal-async`.
+//!
+//! Every register access is a single bus transaction, so the bus is only held for that
+//! transaction and never across an `.await` in between. This makes the driver safe to use with
+//! a shared async bus, e.g. `I2cDevice` over an async mutex, next to other drivers.
+
+// Public imports
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+// Local imports
+use crate::calibration::{Calibration, CALIBRATION_LENGTH, HUMIDITY_CALIBRATION_LENGTH};
+use crate::i2c::constants::{registers, values};
+use crate::{checked_address, compensate_sample, Address, Config, Measurements, Mode, Oversampling, RawMeasurement, SensorError, Status, Variant};
+use crate::{BURST_ATTEMPTS, DATA_FRAME_LENGTH, FORCED_POLL_ATTEMPTS, FORCED_POLL_INTERVAL_US};
+
+
+/// Atmospheric sensor driven through an async I2C bus.
+pub struct AtmosphericSensorAsync<I2C> {
+    i2c: I2C,
+    address: u8,
+    calibration: Calibration,
+    variant: Variant,
+    config: Config,
+    configured: bool,
+}
+
+impl<I2C: I2c> AtmosphericSensorAsync<I2C> {
+    /// Identify the sensor from its chip ID and read its calibration.
+    ///
+    /// The sensor stays in sleep mode, and refuses reads, until `apply_config`.
+    pub async fn new(mut i2c: I2C, address: Address) -> Result<AtmosphericSensorAsync<I2C>, SensorError<I2C::Error>> {
+        let address = checked_address(address)?;
+
+        let mut id = [0u8];
+        read_register(&mut i2c, address, registers::CHIP_ID_REG, &mut id).await?;
+        let variant = Variant::from_chip_id(id[0]).ok_or(SensorError::InvalidChipId(id[0]))?;
+
+        let mut block = [0u8; CALIBRATION_LENGTH];
+        read_register(&mut i2c, address, registers::DIG_T1_LSB_REG, &mut block).await?;
+        let mut humidity = [0u8; HUMIDITY_CALIBRATION_LENGTH];
+        if variant.has_humidity() {
+            read_register(&mut i2c, address, registers::DIG_H2_LSB_REG, &mut humidity).await?;
+        }
+        let calibration = Calibration::from_bytes(&block, variant.has_humidity().then_some(&humidity));
+
+        Ok(AtmosphericSensorAsync { i2c, address, calibration, variant, config: Config::default(), configured: false })
+    }
+
+    /// Sensor variant this driver talks to.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Configuration last written to the sensor.
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Start the sensor in normal mode, like the blocking `start`.
+    pub async fn start(&mut self) -> Result<(), SensorError<I2C::Error>> {
+        self.apply_config(&self.config.started()).await
+    }
+
+    /// Write a configuration to the sensor.
+    ///
+    /// The sensor is put to sleep so the config register write is accepted, then each control
+    /// register is written whole, ctrl_meas last so the humidity setting takes effect.
+    pub async fn apply_config(&mut self, config: &Config) -> Result<(), SensorError<I2C::Error>> {
+        let config = match self.variant.has_humidity() {
+            true => *config,
+            false => Config { humidity_oversampling: Oversampling::Skipped, ..*config },
+        };
+        let (ctrl_hum, ctrl_meas, config_reg) = config.to_registers();
+
+        self.write(registers::CTRL_MEAS_REG, ctrl_meas & 0xFC).await?;
+        self.write(registers::CONFIG_REG, config_reg).await?;
+        if self.variant.has_humidity() {
+            self.write(registers::CTRL_HUMIDITY_REG, ctrl_hum).await?;
+        }
+        self.write(registers::CTRL_MEAS_REG, ctrl_meas).await?;
+
+        self.config = config;
+        self.configured = true;
+        Ok(())
+    }
+
+    /// Read temperature, pressure and humidity from the same sample.
+    ///
+    /// Like the blocking `measure`, the burst is taken again when a conversion finished while
+    /// it was read.
+    pub async fn measure(&mut self) -> Result<Measurements, SensorError<I2C::Error>> {
+        if !self.configured {
+            return Err(SensorError::NotConfigured);
+        }
+        if self.config.mode == Mode::Sleep {
+            return Err(SensorError::Asleep);
+        }
+
+        for _ in 0..BURST_ATTEMPTS {
+            let measuring_before = self.is_measuring().await?;
+            let mut data = [0u8; DATA_FRAME_LENGTH];
+            self.read(registers::PRESSURE_MSB_REG, &mut data).await?;
+            let measuring_after = self.is_measuring().await?;
+
+            let straddled = measuring_before && !measuring_after;
+            if !straddled {
+                let raw = RawMeasurement::from(data);
+                let (sample, _) = compensate_sample(&self.calibration, &raw, &self.config)?;
+                return Ok(sample);
+            }
+        }
+        Err(SensorError::BurstStraddled)
+    }
+
+    /// Take one measurement in forced mode, polling the status register until it is done.
+    ///
+    /// `delay` is awaited between status reads, so the executor can run other tasks while the
+    /// conversion is going on. Oversampling and filter must be configured beforehand.
+    pub async fn measure_forced<D: DelayNs>(&mut self, delay: &mut D) -> Result<Measurements, SensorError<I2C::Error>> {
+        if !self.configured {
+            return Err(SensorError::NotConfigured);
+        }
+        let config = Config { mode: Mode::Forced, ..self.config };
+        let (_, ctrl_meas, _) = config.to_registers();
+        self.write(registers::CTRL_MEAS_REG, ctrl_meas).await?;
+        self.config = config;
+
+        for _ in 0..FORCED_POLL_ATTEMPTS {
+            // Wait first, the measuring bit may not be set right after the mode write
+            delay.delay_us(FORCED_POLL_INTERVAL_US).await;
+            if !self.is_measuring().await? {
+                let mut data = [0u8; DATA_FRAME_LENGTH];
+                self.read(registers::PRESSURE_MSB_REG, &mut data).await?;
+                let (sample, _) = compensate_sample(&self.calibration, &RawMeasurement::from(data), &self.config)?;
+                return Ok(sample);
+            }
+        }
+        Err(SensorError::Timeout)
+    }
+
+    /// Is the device measuring.
+    pub async fn is_measuring(&mut self) -> Result<bool, SensorError<I2C::Error>> {
+        let mut status = [0u8];
+        self.read(registers::STAT_REG, &mut status).await?;
+        Ok(Status::from(status[0]).measuring)
+    }
+
+    /// Reset device.
+    pub async fn reset(&mut self) -> Result<(), SensorError<I2C::Error>> {
+        self.write(registers::RST_REG, values::SOFT_RESET).await?;
+        self.config = Config {
+            temperature_offset_c: self.config.temperature_offset_c,
+            out_of_range: self.config.out_of_range,
+            ..Config::default()
+        };
+        self.configured = false;
+        Ok(())
+    }
+
+    /// Give the bus back.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    async fn read(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), SensorError<I2C::Error>> {
+        read_register(&mut self.i2c, self.address, register, buffer).await
+    }
+
+    async fn write(&mut self, register: u8, value: u8) -> Result<(), SensorError<I2C::Error>> {
+        self.i2c.write(self.address, &[register, value]).await.map_err(SensorError::I2c)
+    }
+}
+
+
+/// Read consecutive registers starting at `register` in one transaction.
+async fn read_register<I2C: I2c>(i2c: &mut I2C, address: u8, register: u8, buffer: &mut [u8]) -> Result<(), SensorError<I2C::Error>> {
+    i2c.write_read(address, &[register], buffer).await
+        .map_err(SensorError::I2c)
+}
@@ -0,0 +1,190 @@
+//! Weather station logic on top of the sensor: sea level pressure and its trend.
+
+// Local imports
+use crate::{derived, AtmosphericSensor, Bus, SensorError};
+
+/// Smoothing factor of the sea level pressure estimate unless configured otherwise.
+const DEFAULT_SMOOTHING: f64 = 0.2;
+
+/// Pressure difference in pascal between the estimate and its baseline beyond which the
+/// pressure counts as rising or falling, unless configured otherwise.
+const DEFAULT_TREND_THRESHOLD_PA: f64 = 50.0;
+
+/// How many times slower the trend baseline follows the readings than the estimate.
+const BASELINE_SLOWDOWN: f64 = 10.0;
+
+
+/// Direction the sea level pressure is heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureTrend {
+    Rising,
+    Steady,
+    Falling
+}
+
+
+/// One reading of the barometer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarometerReading {
+    /// Pressure measured at the station, in pascal.
+    pub pressure_pascal: f64,
+    /// Smoothed sea level pressure estimate, in pascal.
+    pub sea_level_pascal: f64,
+    pub trend: PressureTrend,
+}
+
+
+/// Barometer at a fixed, known altitude.
+///
+/// Every reading is reduced to sea level and folded into an exponentially smoothed estimate.
+/// A second estimate follows the readings ten times slower; the trend compares the two, so a
+/// steady drift of the pressure shows as rising or falling once it exceeds the threshold.
+/// Readings should be taken at a regular interval for the smoothing to mean anything.
+pub struct Barometer<B: Bus> {
+    sensor: AtmosphericSensor<B>,
+    altitude_meters: f64,
+    smoothing: f64,
+    trend_threshold_pa: f64,
+    sea_level_pa: Option<f64>,
+    baseline_pa: Option<f64>,
+}
+
+impl<B: Bus> Barometer<B> {
+    /// Wrap a configured sensor placed at `altitude_meters` above sea level.
+    pub fn new(sensor: AtmosphericSensor<B>, altitude_meters: f64) -> Barometer<B> {
+        Barometer {
+            sensor,
+            altitude_meters,
+            smoothing: DEFAULT_SMOOTHING,
+            trend_threshold_pa: DEFAULT_TREND_THRESHOLD_PA,
+            sea_level_pa: None,
+            baseline_pa: None,
+        }
+    }
+
+    /// Weight between 0 and 1 of each new reading in the estimate; lower is smoother.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Barometer<B> {
+        self.smoothing = smoothing.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    /// Difference in pascal between the estimate and its baseline that counts as a trend.
+    pub fn with_trend_threshold(mut self, threshold_pa: f64) -> Barometer<B> {
+        self.trend_threshold_pa = threshold_pa;
+        self
+    }
+
+    /// Measure the pressure and fold it into the estimate.
+    pub fn update(&mut self) -> Result<BarometerReading, SensorError<B::Error>> {
+        let sample = self.sensor.measure()?;
+        self.record(sample.pressure_pascal)
+    }
+
+    /// Fold a station pressure in pascal, e.g. taken with the sensor directly, into the estimate.
+    ///
+    /// Fails with `SensorError::AltitudeOutOfRange` when the barometer sits too high for the
+    /// pressure to be reduced to sea level.
+    pub fn record(&mut self, pressure_pascal: f64) -> Result<BarometerReading, SensorError<B::Error>> {
+        let sea_level = derived::sea_level_pressure_pascal(pressure_pascal, self.altitude_meters)
+            .ok_or(SensorError::AltitudeOutOfRange)?;
+        let estimate = smooth(self.sea_level_pa, sea_level, self.smoothing);
+        let baseline = smooth(self.baseline_pa, sea_level, self.smoothing / BASELINE_SLOWDOWN);
+        self.sea_level_pa = Some(estimate);
+        self.baseline_pa = Some(baseline);
+
+        Ok(BarometerReading { pressure_pascal, sea_level_pascal: estimate, trend: self.classify(estimate - baseline) })
+    }
+
+    /// Current sea level pressure estimate in pascal, `None` before the first reading.
+    pub fn sea_level_pressure_pa(&self) -> Option<f64> {
+        self.sea_level_pa
+    }
+
+    /// Current pressure trend, `None` before the first reading.
+    pub fn trend(&self) -> Option<PressureTrend> {
+        Some(self.classify(self.sea_level_pa? - self.baseline_pa?))
+    }
+
+    /// Sensor used by the barometer, e.g. to read temperature alongside.
+    pub fn sensor(&mut self) -> &mut AtmosphericSensor<B> {
+        &mut self.sensor
+    }
+
+    /// Give the sensor back.
+    pub fn release(self) -> AtmosphericSensor<B> {
+        self.sensor
+    }
+
+    fn classify(&self, difference_pa: f64) -> PressureTrend {
+        if difference_pa > self.trend_threshold_pa {
+            PressureTrend::Rising
+        } else if difference_pa < -self.trend_threshold_pa {
+            PressureTrend::Falling
+        } else {
+            PressureTrend::Steady
+        }
+    }
+}
+
+
+/// Exponential smoothing step, starting from the first value.
+fn smooth(previous: Option<f64>, value: f64, weight: f64) -> f64 {
+    match previous {
+        Some(previous) => previous + weight * (value - previous),
+        None => value,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{finish, mock_sensor};
+
+    #[test]
+    fn steady_pressure() {
+        let (sensor, mock) = mock_sensor(Vec::new());
+        let mut barometer = Barometer::new(sensor, 500.0);
+        assert_eq!(barometer.trend(), None);
+        let first = barometer.record(95500.0).unwrap();
+        assert_eq!(Some(first.sea_level_pascal), derived::sea_level_pressure_pascal(95500.0, 500.0));
+        for _ in 0..20 {
+            assert_eq!(barometer.record(95500.0).unwrap().trend, PressureTrend::Steady);
+        }
+        assert_eq!(barometer.sea_level_pressure_pa(), Some(first.sea_level_pascal));
+
+        finish(barometer, mock);
+    }
+
+    #[test]
+    fn rising_and_falling_pressure() {
+        let (sensor, mock) = mock_sensor(Vec::new());
+        let mut barometer = Barometer::new(sensor, 500.0).with_trend_threshold(20.0);
+        let mut pressure = 95500.0;
+        for _ in 0..30 {
+            pressure += 5.0;
+            barometer.record(pressure).unwrap();
+        }
+        assert_eq!(barometer.trend(), Some(PressureTrend::Rising));
+        // Smoothing lags behind the readings
+        assert!(barometer.sea_level_pressure_pa().unwrap() < derived::sea_level_pressure_pascal(pressure, 500.0).unwrap());
+
+        for _ in 0..60 {
+            pressure -= 5.0;
+            barometer.record(pressure).unwrap();
+        }
+        assert_eq!(barometer.trend(), Some(PressureTrend::Falling));
+
+        finish(barometer, mock);
+    }
+
+    #[test]
+    fn altitude_out_of_range() {
+        let (sensor, mock) = mock_sensor(Vec::new());
+        let mut barometer = Barometer::new(sensor, 44330.0);
+        assert_eq!(barometer.record(95500.0), Err(SensorError::AltitudeOutOfRange));
+        assert_eq!(barometer.sea_level_pressure_pa(), None);
+
+        finish(barometer, mock);
+    }
+}
@@ -0,0 +1,188 @@
+//! Compensation of the older BMP180 and BMP085.
+//!
+//! They share the chip ID register and the I2C address with the BME280 but have their own
+//! calibration set, AC1 to AC6, B1, B2, MB, MC and MD, and a command based measurement
+//! protocol. Only the calibration and the formulas live here, behind `Compensation`.
+//! `AtmosphericSensor` doesn't drive the BMP180 measurement protocol, so `try_new` refuses its
+//! chip ID 0x55; read the calibration with `Bmp180Calibration::build` and the raw values on
+//! your own.
+
+// Public imports
+use byteorder::{BigEndian, ByteOrder};
+
+// Local imports
+use crate::bus::Bus;
+use crate::calibration::{Compensation, CompensationError};
+use crate::i2c::AtmosphericSensorI2cError;
+
+/// First register of the calibration block, AC1 MSB.
+const CALIBRATION_REG: u8 = 0xAA;
+
+/// Length of the calibration block from 0xAA to 0xBF.
+pub const BMP180_CALIBRATION_LENGTH: usize = 22;
+
+/// Highest oversampling setting, 8 samples.
+const MAX_OVERSAMPLING: u8 = 3;
+
+
+/// Calibration of a BMP180, with the oversampling setting its raw pressures are taken with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bmp180Calibration {
+    ac1: i16,
+    ac2: i16,
+    ac3: i16,
+    ac4: u16,
+    ac5: u16,
+    ac6: u16,
+    b1: i16,
+    b2: i16,
+    mb: i16,
+    mc: i16,
+    md: i16,
+    oversampling: u8,
+}
+
+impl Bmp180Calibration {
+    /// Decode registers 0xAA to 0xBF, big endian words, for ultra low power sampling.
+    pub fn from_bytes(block: &[u8; BMP180_CALIBRATION_LENGTH]) -> Bmp180Calibration {
+        let signed = |index: usize| BigEndian::read_i16(&block[2 * index..2 * index + 2]);
+        let unsigned = |index: usize| BigEndian::read_u16(&block[2 * index..2 * index + 2]);
+        Bmp180Calibration {
+            ac1: signed(0),
+            ac2: signed(1),
+            ac3: signed(2),
+            ac4: unsigned(3),
+            ac5: unsigned(4),
+            ac6: unsigned(5),
+            b1: signed(6),
+            b2: signed(7),
+            mb: signed(8),
+            mc: signed(9),
+            md: signed(10),
+            oversampling: 0,
+        }
+    }
+
+    /// Read the calibration block in one burst.
+    pub fn build<B: Bus>(dev: &mut B) -> Result<Bmp180Calibration, AtmosphericSensorI2cError<B::Error>> {
+        let mut block = [0u8; BMP180_CALIBRATION_LENGTH];
+        dev.read_register(CALIBRATION_REG, &mut block)?;
+        Ok(Bmp180Calibration::from_bytes(&block))
+    }
+
+    /// Use the oversampling setting `oss`, 0 to 3, that raw pressures are taken with.
+    /// Larger values are taken as 3.
+    pub fn with_oversampling(self, oss: u8) -> Bmp180Calibration {
+        Bmp180Calibration { oversampling: oss.min(MAX_OVERSAMPLING), ..self }
+    }
+
+    /// Coefficients AC1 to AC3, AC4 to AC6, B1, B2, MB, MC and MD.
+    pub fn coefficients(&self) -> ([i16; 3], [u16; 3], [i16; 5]) {
+        (
+            [self.ac1, self.ac2, self.ac3],
+            [self.ac4, self.ac5, self.ac6],
+            [self.b1, self.b2, self.mb, self.mc, self.md],
+        )
+    }
+
+    /// B5 of the datasheet from the raw temperature UT.
+    pub fn compensate_temperature(&self, ut: i32) -> Result<i32, CompensationError> {
+        let x1 = ((i64::from(ut) - i64::from(self.ac6)) * i64::from(self.ac5)) >> 15;
+        let x2 = (i64::from(self.mc) << 11)
+            .checked_div(x1 + i64::from(self.md))
+            .ok_or(CompensationError::DivisionByZero)?;
+        i32::try_from(x1 + x2).map_err(|_| CompensationError::ArithmeticOverflow)
+    }
+
+    /// Pressure in pascal from the raw pressure UP, already shifted by `8 - oss`, and B5.
+    pub fn compensate_pressure(&self, up: i32, b5: i32) -> Result<i32, CompensationError> {
+        let oss = u32::from(self.oversampling);
+        let b6 = i64::from(b5) - 4000;
+        let b6_squared = (b6 * b6) >> 12;
+
+        let x3 = ((i64::from(self.b2) * b6_squared) >> 11) + ((i64::from(self.ac2) * b6) >> 11);
+        let b3 = (((i64::from(self.ac1) * 4 + x3) << oss) + 2) / 4;
+        let x3 = (((i64::from(self.ac3) * b6) >> 13) + ((i64::from(self.b1) * b6_squared) >> 16) + 2) >> 2;
+        let b4 = (i64::from(self.ac4) * (x3 + 32768)) >> 15;
+        let b7 = (i64::from(up) - b3) * (50000 >> oss);
+
+        let p = (b7 * 2).checked_div(b4).ok_or(CompensationError::DivisionByZero)?;
+        let x1 = ((p >> 8) * (p >> 8) * 3038) >> 16;
+        let x2 = (-7357 * p) >> 16;
+        i32::try_from(p + ((x1 + x2 + 3791) >> 4)).map_err(|_| CompensationError::PressureOverflow)
+    }
+}
+
+/// The BMP180 formulas, with B5 as the fine temperature.
+impl Compensation for Bmp180Calibration {
+    fn fine_temperature(&self, adc_t: i32) -> Result<i32, CompensationError> {
+        self.compensate_temperature(adc_t)
+    }
+
+    fn temperature_celsius(&self, fine_temperature: i32) -> f64 {
+        // 0.1 degree steps
+        f64::from((fine_temperature + 8) >> 4) / 10.0
+    }
+
+    fn pressure_pascal(&self, adc_p: i32, fine_temperature: i32) -> Result<f64, CompensationError> {
+        Ok(f64::from(self.compensate_pressure(adc_p, fine_temperature)?))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::{Calibration, PressureCalibration, TemperatureCalibration};
+
+    /// Calibration of the worked example in the datasheet, section 3.5.
+    fn datasheet_calibration() -> Bmp180Calibration {
+        let words: [i16; 11] = [408, -72, -14383, 32741, 32757, 23153, 6190, 4, -32768, -8711, 2868];
+        let mut block = [0u8; BMP180_CALIBRATION_LENGTH];
+        for (chunk, word) in block.chunks_exact_mut(2).zip(words) {
+            BigEndian::write_i16(chunk, word);
+        }
+        Bmp180Calibration::from_bytes(&block)
+    }
+
+    #[test]
+    fn datasheet_example() {
+        let calibration = datasheet_calibration();
+        assert_eq!(calibration.coefficients().1, [32741, 32757, 23153]);
+
+        // The datasheet rounds X2 to -2344 where the integer division gives -2343, so B5 is
+        // 2400 instead of 2399; temperature and pressure still match
+        let b5 = calibration.compensate_temperature(27898).unwrap();
+        assert_eq!(b5, 2400);
+        assert_eq!(calibration.temperature_celsius(b5), 15.0);
+        assert_eq!(calibration.compensate_pressure(23843, b5), Ok(69964));
+    }
+
+    #[test]
+    fn pluggable_compensation() {
+        fn pressure(compensation: &impl Compensation, adc_t: i32, adc_p: i32) -> f64 {
+            let fine = compensation.fine_temperature(adc_t).unwrap();
+            compensation.pressure_pascal(adc_p, fine).unwrap()
+        }
+
+        assert_eq!(pressure(&datasheet_calibration(), 27898, 23843), 69964.0);
+        // The coefficients served by the mock calibration of the driver tests, with the raw
+        // temperature giving t_fine 120035 of the pressure calibration tests
+        let bme280 = Calibration::new(
+            TemperatureCalibration::new(28485, 26735, 50),
+            PressureCalibration::new(36738, -10635, 3024, 6980, -4, -7, 9900, -10230, 4285),
+            None,
+        );
+        assert_eq!(bme280.fine_temperature(529312), Ok(120035));
+        assert_eq!(pressure(&bme280, 529312, 337136), 26036801.0 / 256.0);
+    }
+
+    #[test]
+    fn blank_calibration() {
+        let blank = Bmp180Calibration::from_bytes(&[0u8; BMP180_CALIBRATION_LENGTH]);
+        assert_eq!(blank.compensate_temperature(27898), Err(CompensationError::DivisionByZero));
+        assert_eq!(blank.compensate_pressure(23843, 2399), Err(CompensationError::DivisionByZero));
+        assert_eq!(datasheet_calibration().with_oversampling(7).oversampling, MAX_OVERSAMPLING);
+    }
+}
@@ -0,0 +1,161 @@
+//! Register access shared by the I2C and SPI transports.
+
+// Local imports
+#[cfg(feature = "sleep-on-drop")]
+use crate::i2c::Registers;
+
+
+/// Bus the sensor registers are read and written through.
+///
+/// Register addresses are the ones from the datasheet; each transport adds whatever framing it
+/// needs around them.
+pub trait Bus {
+    /// Error reported by the underlying bus.
+    type Error;
+
+    /// Read consecutive registers starting at `register` in one transaction.
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write `value` to `register`.
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Self::Error>;
+}
+
+
+/// Direction of a traced transaction.
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Read,
+    Write
+}
+
+/// Transaction reported to the trace callback once it completed.
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub struct TraceEvent<'a> {
+    pub direction: Direction,
+    /// First register of the transaction.
+    pub register: u8,
+    /// Bytes received for a read, or sent after the register for a write.
+    pub bytes: &'a [u8],
+    /// The bus reported success.
+    pub ok: bool,
+}
+
+
+/// Bus that reports every register transaction to a callback, for protocol debugging.
+///
+/// It wraps either transport, so SPI is traced like I2C. The callback is any closure, so it
+/// can capture a logger or a buffer to copy the events into.
+#[cfg(feature = "trace")]
+pub struct Traced<B, F> {
+    bus: B,
+    callback: F,
+}
+
+#[cfg(feature = "trace")]
+impl<B: Bus, F: FnMut(&TraceEvent)> Traced<B, F> {
+    /// Call `callback` after every transaction on `bus`.
+    pub fn new(bus: B, callback: F) -> Traced<B, F> {
+        Traced { bus, callback }
+    }
+
+    /// Give the bus and the callback back.
+    pub fn release(self) -> (B, F) {
+        (self.bus, self.callback)
+    }
+
+    /// Wrapped bus, talking to it directly isn't traced.
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+}
+
+#[cfg(feature = "trace")]
+impl<B: Bus, F: FnMut(&TraceEvent)> Bus for Traced<B, F> {
+    type Error = B::Error;
+
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let result = self.bus.read_register(register, buffer);
+        (self.callback)(&TraceEvent { direction: Direction::Read, register, bytes: buffer, ok: result.is_ok() });
+        result
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        let result = self.bus.write_register(register, value);
+        (self.callback)(&TraceEvent { direction: Direction::Write, register, bytes: &[value], ok: result.is_ok() });
+        result
+    }
+}
+
+
+/// Bus of a driver built with `sleep-on-drop`, putting the sensor to sleep when dropped.
+///
+/// Best effort: `drop` cannot report errors, so a failed bus write leaves the sensor in the
+/// mode it was in. Call `stop` beforehand when the outcome matters. The bus sits in an
+/// `Option` so `into_inner` can move it out; it is only ever `None` while being dropped.
+#[cfg(feature = "sleep-on-drop")]
+pub(crate) struct SleepOnDrop<B: Bus>(Option<B>);
+
+#[cfg(feature = "sleep-on-drop")]
+impl<B: Bus> SleepOnDrop<B> {
+    /// Put the sensor to sleep like `drop` does, and hand the bus out.
+    pub(crate) fn into_inner(mut self) -> B {
+        let mut dev = self.0.take().expect("bus is only taken once");
+        let _ = dev.sleep();
+        dev
+    }
+}
+
+#[cfg(all(feature = "sleep-on-drop", feature = "trace"))]
+impl<B: Bus> SleepOnDrop<B> {
+    /// Wrap the bus in another one, keeping the sensor awake.
+    pub(crate) fn map<C: Bus>(mut self, wrap: impl FnOnce(B) -> C) -> SleepOnDrop<C> {
+        SleepOnDrop(self.0.take().map(wrap))
+    }
+}
+
+#[cfg(feature = "sleep-on-drop")]
+impl<B: Bus> From<B> for SleepOnDrop<B> {
+    fn from(dev: B) -> Self {
+        SleepOnDrop(Some(dev))
+    }
+}
+
+#[cfg(feature = "sleep-on-drop")]
+impl<B: Bus> core::ops::Deref for SleepOnDrop<B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        self.0.as_ref().expect("bus is only taken once")
+    }
+}
+
+#[cfg(feature = "sleep-on-drop")]
+impl<B: Bus> core::ops::DerefMut for SleepOnDrop<B> {
+    fn deref_mut(&mut self) -> &mut B {
+        self.0.as_mut().expect("bus is only taken once")
+    }
+}
+
+#[cfg(feature = "sleep-on-drop")]
+impl<B: Bus> Bus for SleepOnDrop<B> {
+    type Error = B::Error;
+
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        (**self).read_register(register, buffer)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        (**self).write_register(register, value)
+    }
+}
+
+#[cfg(feature = "sleep-on-drop")]
+impl<B: Bus> Drop for SleepOnDrop<B> {
+    fn drop(&mut self) {
+        if let Some(dev) = &mut self.0 {
+            let _ = dev.sleep();
+        }
+    }
+}
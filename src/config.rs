@@ -0,0 +1,330 @@
+//! Sensor configuration.
+
+// Local imports
+use crate::i2c::{Filter, Mode, Oversampling, StandbyTime};
+use crate::measurement::Channel;
+use crate::{ranges, SensorError};
+
+
+/// What to do with a reading outside the operating range of its channel, see `ranges`.
+///
+/// Defaults to passing the reading through untouched.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfRange {
+    /// Bring the reading back to the closest bound.
+    Clamp,
+    /// Fail with `SensorError::OutOfRange`.
+    Error,
+    #[default]
+    Passthrough,
+}
+
+impl OutOfRange {
+    /// Apply the policy to a reading of `channel`. NaN, from a skipped channel, is left as is.
+    pub fn apply<E>(&self, channel: Channel, value: f64) -> Result<f64, SensorError<E>> {
+        let range = match channel {
+            Channel::Temperature => ranges::TEMPERATURE_CELSIUS,
+            Channel::Pressure => ranges::PRESSURE_PASCAL,
+            Channel::Humidity => ranges::HUMIDITY_PERCENT,
+        };
+        if value.is_nan() || range.contains(&value) {
+            return Ok(value);
+        }
+        match self {
+            OutOfRange::Clamp => Ok(value.clamp(*range.start(), *range.end())),
+            OutOfRange::Error => Err(SensorError::OutOfRange(channel)),
+            OutOfRange::Passthrough => Ok(value),
+        }
+    }
+}
+
+
+/// Recommended operating modes from the datasheet, section 3.5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Profile {
+    /// See `Config::weather_monitoring`.
+    WeatherMonitoring,
+    /// See `Config::humidity_sensing`.
+    HumiditySensing,
+    /// See `Config::indoor_navigation`.
+    IndoorNavigation,
+    /// See `Config::gaming`.
+    Gaming,
+}
+
+impl Profile {
+    /// Configuration of the profile, with no temperature offset and readings passed through.
+    pub fn config(self) -> Config {
+        match self {
+            Profile::WeatherMonitoring => Config::weather_monitoring(),
+            Profile::HumiditySensing => Config::humidity_sensing(),
+            Profile::IndoorNavigation => Config::indoor_navigation(),
+            Profile::Gaming => Config::gaming(),
+        }
+    }
+}
+
+
+/// Settings written to the control registers of the sensor, plus the software trims
+/// applied to its readings.
+///
+/// The default is the power-on state of the sensor: sleep mode, every channel skipped, filter
+/// off and no trim.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub mode: Mode,
+    pub temperature_oversampling: Oversampling,
+    pub pressure_oversampling: Oversampling,
+    pub humidity_oversampling: Oversampling,
+    pub filter: Filter,
+    pub standby_time: StandbyTime,
+    /// Celsius subtracted from the measured temperature, to trim a unit against a reference.
+    /// It is applied before pressure and humidity compensation so they stay consistent.
+    pub temperature_offset_c: f64,
+    /// Handling of readings outside the operating ranges, applied by every getter, the integer
+    /// `read_display_tuple` included.
+    pub out_of_range: OutOfRange,
+}
+
+impl Config {
+    /// Decode the configuration held in the ctrl_hum, ctrl_meas and config registers.
+    pub fn from_registers(ctrl_hum: u8, ctrl_meas: u8, config: u8) -> Config {
+        Config {
+            mode: Mode::from_field(ctrl_meas & 0x03),
+            temperature_oversampling: Oversampling::from_field(ctrl_meas >> 5),
+            pressure_oversampling: Oversampling::from_field((ctrl_meas >> 2) & 0x07),
+            humidity_oversampling: Oversampling::from_field(ctrl_hum & 0x07),
+            filter: Filter::from_field((config >> 2) & 0x07),
+            standby_time: StandbyTime::from_field(config >> 5),
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+
+    /// Encode the configuration into the ctrl_hum, ctrl_meas and config registers.
+    pub fn to_registers(&self) -> (u8, u8, u8) {
+        let ctrl_hum = u8::from(self.humidity_oversampling);
+        let ctrl_meas = (u8::from(self.temperature_oversampling) << 5)
+            | (u8::from(self.pressure_oversampling) << 2)
+            | u8::from(self.mode);
+        let config = (u8::from(self.standby_time) << 5) | (u8::from(self.filter) << 2);
+        (ctrl_hum, ctrl_meas, config)
+    }
+
+    /// Configuration `start` writes: normal mode, 1x oversampling, filter off and the shortest
+    /// standby, keeping the offset and range policy of `self`.
+    pub(crate) fn started(&self) -> Config {
+        self.mode(Mode::Normal)
+            .temperature_oversampling(Oversampling::Ox1)
+            .pressure_oversampling(Oversampling::Ox1)
+            .humidity_oversampling(Oversampling::Ox1)
+            .filter(Filter::Off)
+            .standby_time(StandbyTime::Ms0_5)
+    }
+
+    /// Set the mode.
+    pub fn mode(mut self, mode: Mode) -> Config {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the temperature oversampling.
+    pub fn temperature_oversampling(mut self, rate: Oversampling) -> Config {
+        self.temperature_oversampling = rate;
+        self
+    }
+
+    /// Set the pressure oversampling.
+    pub fn pressure_oversampling(mut self, rate: Oversampling) -> Config {
+        self.pressure_oversampling = rate;
+        self
+    }
+
+    /// Set the humidity oversampling, ignored on variants without humidity.
+    pub fn humidity_oversampling(mut self, rate: Oversampling) -> Config {
+        self.humidity_oversampling = rate;
+        self
+    }
+
+    /// Set the IIR filter coefficient.
+    pub fn filter(mut self, filter: Filter) -> Config {
+        self.filter = filter;
+        self
+    }
+
+    /// Set the standby time between conversions in normal mode.
+    pub fn standby_time(mut self, standby: StandbyTime) -> Config {
+        self.standby_time = standby;
+        self
+    }
+
+    /// Low power preset: normal mode with the longest standby, 1x oversampling and filter off.
+    pub fn low_power() -> Config {
+        Config {
+            mode: Mode::Normal,
+            temperature_oversampling: Oversampling::Ox1,
+            pressure_oversampling: Oversampling::Ox1,
+            humidity_oversampling: Oversampling::Ox1,
+            filter: Filter::Off,
+            standby_time: StandbyTime::Ms1000,
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+
+    /// High accuracy preset: forced mode, 16x pressure and 4x temperature/humidity
+    /// oversampling with the IIR filter at 16.
+    pub fn high_accuracy() -> Config {
+        Config {
+            mode: Mode::Forced,
+            temperature_oversampling: Oversampling::Ox4,
+            pressure_oversampling: Oversampling::Ox16,
+            humidity_oversampling: Oversampling::Ox4,
+            filter: Filter::C16,
+            standby_time: StandbyTime::Ms0_5,
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+
+    /// Weather monitoring preset from the datasheet: forced mode, 1x oversampling on every
+    /// channel and filter off.
+    pub fn weather_monitoring() -> Config {
+        Config {
+            mode: Mode::Forced,
+            temperature_oversampling: Oversampling::Ox1,
+            pressure_oversampling: Oversampling::Ox1,
+            humidity_oversampling: Oversampling::Ox1,
+            filter: Filter::Off,
+            standby_time: StandbyTime::Ms0_5,
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+
+    /// Humidity sensing preset from the datasheet: forced mode, pressure skipped, 1x
+    /// temperature and humidity oversampling and filter off.
+    pub fn humidity_sensing() -> Config {
+        Config {
+            mode: Mode::Forced,
+            temperature_oversampling: Oversampling::Ox1,
+            pressure_oversampling: Oversampling::Skipped,
+            humidity_oversampling: Oversampling::Ox1,
+            filter: Filter::Off,
+            standby_time: StandbyTime::Ms0_5,
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+
+    /// Indoor navigation preset from the datasheet: normal mode with 0.5ms standby, 16x
+    /// pressure, 2x temperature and 1x humidity oversampling and filter at 16.
+    pub fn indoor_navigation() -> Config {
+        Config {
+            mode: Mode::Normal,
+            temperature_oversampling: Oversampling::Ox2,
+            pressure_oversampling: Oversampling::Ox16,
+            humidity_oversampling: Oversampling::Ox1,
+            filter: Filter::C16,
+            standby_time: StandbyTime::Ms0_5,
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+
+    /// Gaming preset from the datasheet: normal mode with 0.5ms standby, 4x pressure and 1x
+    /// temperature oversampling, humidity skipped and filter at 16.
+    pub fn gaming() -> Config {
+        Config {
+            mode: Mode::Normal,
+            temperature_oversampling: Oversampling::Ox1,
+            pressure_oversampling: Oversampling::Ox4,
+            humidity_oversampling: Oversampling::Skipped,
+            filter: Filter::C16,
+            standby_time: StandbyTime::Ms0_5,
+            temperature_offset_c: 0.0,
+            out_of_range: OutOfRange::Passthrough,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_registers() {
+        // Indoor navigation: t 2x, p 16x, normal / h 1x / standby 0.5ms, filter 16
+        let config = Config::from_registers(0x01, 0x57, 0x10);
+        assert_eq!(config, Config::indoor_navigation());
+    }
+
+    #[test]
+    fn decode_ignores_unrelated_bits() {
+        // spi3w_en in config and the reserved ctrl_hum bits don't leak into the settings
+        let config = Config::from_registers(0xF9, 0x27, 0xA1);
+        assert_eq!(config, Config::low_power());
+    }
+
+    #[test]
+    fn encode_registers() {
+        assert_eq!(Config::indoor_navigation().to_registers(), (0x01, 0x57, 0x10));
+        for preset in [Config::low_power(), Config::high_accuracy(), Config::weather_monitoring(), Config::gaming()] {
+            let (ctrl_hum, ctrl_meas, config) = preset.to_registers();
+            assert_eq!(Config::from_registers(ctrl_hum, ctrl_meas, config), preset);
+        }
+    }
+
+    #[test]
+    fn profiles() {
+        assert_eq!(Profile::WeatherMonitoring.config().to_registers(), (0x01, 0x25, 0x00));
+        assert_eq!(Profile::HumiditySensing.config().to_registers(), (0x01, 0x21, 0x00));
+        assert_eq!(Profile::IndoorNavigation.config().to_registers(), (0x01, 0x57, 0x10));
+        assert_eq!(Profile::Gaming.config().to_registers(), (0x00, 0x2F, 0x10));
+        assert_eq!(Config::high_accuracy().mode, Mode::Forced);
+        assert_eq!(Config::high_accuracy().to_registers(), (0x03, 0x75, 0x10));
+        assert_eq!(Config::low_power().mode, Mode::Normal);
+    }
+
+    #[test]
+    fn builder() {
+        let config = Config::default()
+            .mode(Mode::Normal)
+            .temperature_oversampling(Oversampling::Ox16)
+            .pressure_oversampling(Oversampling::Ox16)
+            .humidity_oversampling(Oversampling::Ox1)
+            .filter(Filter::C16)
+            .standby_time(StandbyTime::Ms1000);
+        assert_eq!(config, Config {
+            mode: Mode::Normal,
+            temperature_oversampling: Oversampling::Ox16,
+            pressure_oversampling: Oversampling::Ox16,
+            humidity_oversampling: Oversampling::Ox1,
+            filter: Filter::C16,
+            standby_time: StandbyTime::Ms1000,
+            ..Config::default()
+        });
+        assert_eq!(config.to_registers(), (0x01, 0xB7, 0xB0));
+    }
+
+    #[test]
+    fn out_of_range_policy() {
+        // No bus is involved, so any error type does
+        let apply = |policy: OutOfRange, channel, value| policy.apply::<()>(channel, value);
+        assert_eq!(apply(OutOfRange::Passthrough, Channel::Temperature, 90.0), Ok(90.0));
+        assert_eq!(apply(OutOfRange::Clamp, Channel::Temperature, 90.0), Ok(85.0));
+        assert_eq!(apply(OutOfRange::Clamp, Channel::Humidity, -0.5), Ok(0.0));
+        assert_eq!(apply(OutOfRange::Error, Channel::Pressure, 25000.0), Err(SensorError::OutOfRange(Channel::Pressure)));
+        // In range and skipped readings go through under every policy
+        assert_eq!(apply(OutOfRange::Error, Channel::Pressure, 101325.0), Ok(101325.0));
+        assert!(apply(OutOfRange::Error, Channel::Humidity, f64::NAN).unwrap().is_nan());
+    }
+
+    #[test]
+    fn default_is_power_on_state() {
+        // Every control register reads 0x00 after reset
+        assert_eq!(Config::default(), Config::from_registers(0x00, 0x00, 0x00));
+    }
+}
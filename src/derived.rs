@@ -0,0 +1,209 @@
+//! Quantities derived from the compensated readings.
+
+/// Exponent of the international barometric formula.
+const BAROMETRIC_EXPONENT: f64 = 5.255;
+
+/// Magnus coefficients (Sonntag 1990) for saturation vapour pressure over water.
+const MAGNUS_B: f64 = 17.62;
+const MAGNUS_C: f64 = 243.12;
+
+/// Saturation vapour pressure at 0 celsius in hectopascal.
+const MAGNUS_A: f64 = 6.112;
+
+/// Magnus coefficients (Bolton 1980) of the common absolute humidity approximation.
+const BOLTON_B: f64 = 17.67;
+const BOLTON_C: f64 = 243.5;
+
+/// Water vapour density per unit of vapour pressure and temperature, in g*K/(m³*hPa).
+const VAPOUR_DENSITY_FACTOR: f64 = 216.74;
+
+/// Specific gas constants of dry air and water vapour in J/(kg*K).
+const DRY_AIR_GAS_CONSTANT: f64 = 287.058;
+const WATER_VAPOUR_GAS_CONSTANT: f64 = 461.495;
+
+/// Zero celsius in kelvin.
+const ZERO_CELSIUS_KELVIN: f64 = 273.15;
+
+/// Heat index in fahrenheit from which the NWS switches from the simple formula to the
+/// Rothfusz regression, compared against the mean of the simple formula and the temperature.
+const HEAT_INDEX_ROTHFUSZ_FAHRENHEIT: f64 = 80.0;
+
+
+/// Altitude in meters for a pressure, given the pressure at sea level (both in pascal).
+pub fn altitude_meters(pressure_pascal: f64, sea_level_pascal: f64) -> f64 {
+    44330.0 * (1.0 - (pressure_pascal / sea_level_pascal).powf(1.0 / BAROMETRIC_EXPONENT))
+}
+
+/// Pressure reduced to sea level in pascal, from a pressure in pascal measured at an altitude
+/// in meters. Inverse of `altitude_meters`.
+///
+/// `None` from 44330 meters up, where the formula divides by zero or goes negative.
+pub fn sea_level_pressure_pascal(pressure_pascal: f64, altitude_meters: f64) -> Option<f64> {
+    let base = 1.0 - altitude_meters / 44330.0;
+    match base > 0.0 {
+        true => Some(pressure_pascal / base.powf(BAROMETRIC_EXPONENT)),
+        false => None,
+    }
+}
+
+/// Dew point in celsius from temperature in celsius and relative humidity in percent.
+pub fn dew_point_celsius(temperature_celsius: f64, humidity_percent: f64) -> f64 {
+    let gamma = (humidity_percent / 100.0).ln()
+        + (MAGNUS_B * temperature_celsius) / (MAGNUS_C + temperature_celsius);
+    (MAGNUS_C * gamma) / (MAGNUS_B - gamma)
+}
+
+/// Saturation vapour pressure over water in hectopascal at a temperature in celsius.
+pub fn saturation_vapour_pressure_hpa(temperature_celsius: f64) -> f64 {
+    MAGNUS_A * ((MAGNUS_B * temperature_celsius) / (MAGNUS_C + temperature_celsius)).exp()
+}
+
+/// Absolute humidity in g/m³ from temperature in celsius and relative humidity in percent.
+pub fn absolute_humidity_gm3(temperature_celsius: f64, humidity_percent: f64) -> f64 {
+    let vapour_pressure = saturation_vapour_pressure_hpa(temperature_celsius) * humidity_percent / 100.0;
+    VAPOUR_DENSITY_FACTOR * vapour_pressure / (ZERO_CELSIUS_KELVIN + temperature_celsius)
+}
+
+/// Absolute humidity in g/m³ from temperature in celsius and relative humidity in percent,
+/// with the Bolton coefficients: `6.112 * e^(17.67 * T / (T + 243.5)) * RH * 2.1674 / (273.15 + T)`.
+///
+/// Within a few hundredths of `absolute_humidity_gm3` at room temperature.
+pub fn absolute_humidity_bolton_gm3(temperature_celsius: f64, humidity_percent: f64) -> f64 {
+    let saturation = MAGNUS_A * ((BOLTON_B * temperature_celsius) / (BOLTON_C + temperature_celsius)).exp();
+    saturation * humidity_percent * VAPOUR_DENSITY_FACTOR / 100.0 / (ZERO_CELSIUS_KELVIN + temperature_celsius)
+}
+
+/// Relative humidity in percent that an absolute humidity in g/m³ has at a temperature in
+/// celsius.
+///
+/// Values above 100 mean the air would be supersaturated, i.e. water condenses at that
+/// temperature.
+pub fn relative_humidity_percent(absolute_humidity_gm3: f64, temperature_celsius: f64) -> f64 {
+    let vapour_pressure = absolute_humidity_gm3 * (ZERO_CELSIUS_KELVIN + temperature_celsius) / VAPOUR_DENSITY_FACTOR;
+    100.0 * vapour_pressure / saturation_vapour_pressure_hpa(temperature_celsius)
+}
+
+/// Density of moist air in kg/m³ from temperature in celsius, pressure in pascal and relative
+/// humidity in percent.
+///
+/// Dry air and water vapour are treated as ideal gases sharing the pressure, so humid air is
+/// lighter than dry air at the same temperature and pressure.
+pub fn air_density_kgm3(temperature_celsius: f64, pressure_pascal: f64, humidity_percent: f64) -> f64 {
+    let temperature_kelvin = ZERO_CELSIUS_KELVIN + temperature_celsius;
+    let vapour_pressure = 100.0 * saturation_vapour_pressure_hpa(temperature_celsius) * humidity_percent / 100.0;
+    let dry_pressure = pressure_pascal - vapour_pressure;
+    dry_pressure / (DRY_AIR_GAS_CONSTANT * temperature_kelvin)
+        + vapour_pressure / (WATER_VAPOUR_GAS_CONSTANT * temperature_kelvin)
+}
+
+/// NOAA heat index, the apparent temperature in celsius, from temperature in celsius and
+/// relative humidity in percent.
+///
+/// Follows the NWS procedure: the simple formula, unless its mean with the temperature reaches
+/// 80 fahrenheit, in which case the Rothfusz regression with the NOAA adjustments for below
+/// 13% and above 85% relative humidity.
+pub fn heat_index_celsius(temperature_celsius: f64, humidity_percent: f64) -> f64 {
+    // Both formulas are fitted in fahrenheit
+    let t = temperature_celsius * 1.8 + 32.0;
+    let rh = humidity_percent;
+
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let index = if (simple + t) / 2.0 < HEAT_INDEX_ROTHFUSZ_FAHRENHEIT {
+        simple
+    } else {
+        let index = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+            - 0.224_755_41 * t * rh - 0.006_837_83 * t * t - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            index - (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt()
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            index + (rh - 85.0) / 10.0 * (87.0 - t) / 5.0
+        } else {
+            index
+        }
+    };
+    (index - 32.0) / 1.8
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn altitude_at_sea_level() {
+        assert!(altitude_meters(101325.0, 101325.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn altitude_at_known_pressure() {
+        let altitude = altitude_meters(89874.6, 101325.0);
+        assert!((altitude - 1000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn sea_level_pressure_round_trip() {
+        let sea_level = sea_level_pressure_pascal(89874.6, 1000.0).unwrap();
+        assert!((sea_level - 101325.0).abs() < 15.0);
+        assert!((altitude_meters(89874.6, sea_level) - 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sea_level_pressure_out_of_range() {
+        assert_eq!(sea_level_pressure_pascal(89874.6, 44330.0), None);
+        assert_eq!(sea_level_pressure_pascal(89874.6, 50000.0), None);
+        assert_eq!(sea_level_pressure_pascal(89874.6, f64::NAN), None);
+    }
+
+    #[test]
+    fn dew_point_at_known_values() {
+        let dew_point = dew_point_celsius(25.0, 60.0);
+        assert!((dew_point - 16.69).abs() < 0.05);
+        // Saturated air condenses at its own temperature.
+        assert!((dew_point_celsius(10.0, 100.0) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn absolute_humidity_at_known_values() {
+        // 20 celsius at 50% holds about 8.6 g/m³
+        assert!((absolute_humidity_gm3(20.0, 50.0) - 8.63).abs() < 0.05);
+        // 25 celsius at 60%, worked by hand
+        assert!((absolute_humidity_bolton_gm3(25.0, 60.0) - 13.8154).abs() < 1e-4);
+        assert!((absolute_humidity_bolton_gm3(20.0, 50.0) - absolute_humidity_gm3(20.0, 50.0)).abs() < 0.05);
+    }
+
+    #[test]
+    fn relative_humidity_round_trip() {
+        let absolute = absolute_humidity_gm3(20.0, 50.0);
+        assert!((relative_humidity_percent(absolute, 20.0) - 50.0).abs() < 1e-9);
+        // Cooling the same air raises its relative humidity
+        assert!(relative_humidity_percent(absolute, 10.0) > 90.0);
+        // Below the dew point it would condense
+        let dew_point = dew_point_celsius(20.0, 50.0);
+        assert!(relative_humidity_percent(absolute, dew_point - 1.0) > 100.0);
+    }
+
+    #[test]
+    fn heat_index_at_known_values() {
+        // NOAA table: 90 F at 70% feels like 106 F, 86 F at 90% like 105 F
+        assert!((heat_index_celsius(32.0, 70.0) - 40.4).abs() < 0.1);
+        assert!((heat_index_celsius(30.0, 90.0) - 40.8).abs() < 0.1);
+        // NWS calculator: 89.6 F at 25% feels like 86.6 F
+        assert!((heat_index_celsius(32.0, 25.0) - 30.32).abs() < 0.05);
+        // Low humidity adjustment, 100.4 F at 10% feels like 94.5 F
+        assert!((heat_index_celsius(38.0, 10.0) - 34.73).abs() < 0.05);
+        // Simple formula when cool
+        assert!((heat_index_celsius(20.0, 50.0) - 19.36).abs() < 0.01);
+    }
+
+    #[test]
+    fn air_density_at_known_values() {
+        // Standard atmosphere at sea level
+        assert!((air_density_kgm3(15.0, 101325.0, 0.0) - 1.225).abs() < 0.001);
+        // Water vapour displaces heavier dry air
+        let moist = air_density_kgm3(25.0, 101325.0, 50.0);
+        assert!((moist - 1.177).abs() < 0.001);
+        assert!(moist < air_density_kgm3(25.0, 101325.0, 0.0));
+    }
+}
@@ -2,40 +2,121 @@
 pub mod constants;
 
 // Public imports
+use core::fmt;
 use embedded_hal::i2c::I2c;
 use byteorder::{LittleEndian, ByteOrder};
 
 // Local imports
-use constants::{registers, values, addresses};
+use constants::{registers, values, addresses, Register};
+use crate::bus::Bus;
+use crate::measurement::DATA_FRAME_LENGTH;
 
 
-/// Errors linked to I2c module.
+/// Errors linked to I2c module, carrying the error of the bus.
 #[derive(Debug)]
-pub enum AtmosphericSensorI2cError{
-    IOError
+pub enum AtmosphericSensorI2cError<E> {
+    IOError(E)
+}
+
+impl<E> From<E> for AtmosphericSensorI2cError<E> {
+    fn from(error: E) -> Self {
+        AtmosphericSensorI2cError::IOError(error)
+    }
+}
+
+
+/// Sensors sharing this register map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Variant {
+    Bme280,
+    Bmp280
+}
+
+impl Variant {
+    /// Identify the sensor from its chip ID, `None` for an unknown ID.
+    pub fn from_chip_id(id: u8) -> Option<Variant> {
+        match id {
+            values::CHIP_ID_BME280 => Some(Variant::Bme280),
+            values::CHIP_ID_BMP280 | values::CHIP_ID_BMP280_SAMPLE_1 | values::CHIP_ID_BMP280_SAMPLE_2 => {
+                Some(Variant::Bmp280)
+            }
+            _ => None
+        }
+    }
+
+    /// Whether the sensor has the humidity channel, its calibration and ctrl_hum.
+    pub fn has_humidity(&self) -> bool {
+        match self {
+            Variant::Bme280 => true,
+            Variant::Bmp280 => false,
+        }
+    }
 }
 
 
-/// Modes for the sensor.
+/// A register field value that doesn't fit the field, from the `try_from_field` conversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidFieldValue(pub u8);
+
+impl fmt::Display for InvalidFieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid register field value {:#04x}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidFieldValue {}
+
+/// `value` when no bits outside `mask` are set.
+fn checked_field(value: u8, mask: u8) -> Result<u8, InvalidFieldValue> {
+    match value & !mask {
+        0 => Ok(value),
+        _ => Err(InvalidFieldValue(value)),
+    }
+}
+
+
+/// Modes for the sensor. Defaults to sleep, the power-on state.
+///
+/// The mode field codes 1 and 2 both mean forced. Decoding maps either to `Forced`, which
+/// encodes back as 1, so a mode read back and written again stays forced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Mode {
+    #[default]
     Sleep,
     Forced,
     Normal
 }
 
-impl From<u8> for Mode {
-    /// Convert modes from u8 to Mode.
-    fn from(item: u8) -> Self {
-        match item {
-            0 => Self::Sleep,
-            1 => Self::Forced,
-            2 => Self::Forced,
-            3 => Self::Normal,
-            _ => panic!("Not expected")
+impl Mode {
+    /// Decode the 2-bit mode field, bits above it are ignored. Both forced codes map to forced.
+    pub(crate) fn from_field(bits: u8) -> Mode {
+        match bits & 0x03 {
+            0 => Mode::Sleep,
+            3 => Mode::Normal,
+            _ => Mode::Forced,
         }
     }
 }
 
+impl Mode {
+    /// Decode a mode field read off the wire, failing on values that don't fit its 2 bits.
+    pub fn try_from_field(value: u8) -> Result<Mode, InvalidFieldValue> {
+        checked_field(value, 0x03).map(Mode::from_field)
+    }
+}
+
+impl From<u8> for Mode {
+    /// Decode a mode field, bits above its 2 bits are ignored. See `try_from_field` for
+    /// a conversion that rejects them.
+    fn from(value: u8) -> Self {
+        Mode::from_field(value)
+    }
+}
+
 impl From<Mode> for u8 {
     /// Convert modes from Mode to u8.
     fn from(item: Mode) -> u8 {
@@ -47,9 +128,23 @@ impl From<Mode> for u8 {
     }
 }
 
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mode::Sleep => "Sleep",
+            Mode::Forced => "Forced",
+            Mode::Normal => "Normal",
+        };
+        f.write_str(name)
+    }
+}
+
 
-/// Oversampling on the sensor.
+/// Oversampling on the sensor. Defaults to skipped, the power-on state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Oversampling {
+    #[default]
     Skipped,
     Ox1,  // new freq = freq x 1
     Ox2,  // ...
@@ -58,10 +153,25 @@ pub enum Oversampling {
     Ox16
 }
 
-impl From<u8> for Oversampling {
-    /// Convert from u8 to Oversampling.
-    fn from(value: u8) -> Self {
-        match value {
+impl Oversampling {
+    /// Number of samples averaged per measurement, zero when skipped.
+    pub fn factor(&self) -> u32 {
+        match self {
+            Oversampling::Skipped => 0,
+            Oversampling::Ox1 => 1,
+            Oversampling::Ox2 => 2,
+            Oversampling::Ox4 => 4,
+            Oversampling::Ox8 => 8,
+            Oversampling::Ox16 => 16,
+        }
+    }
+}
+
+impl Oversampling {
+    /// Decode the 3-bit oversampling field, bits above it are ignored. Codes above 4 all
+    /// mean 16x.
+    pub(crate) fn from_field(bits: u8) -> Oversampling {
+        match bits & 0x07 {
             0 => Oversampling::Skipped,
             1 => Oversampling::Ox1,
             2 => Oversampling::Ox2,
@@ -72,6 +182,21 @@ impl From<u8> for Oversampling {
     }
 }
 
+impl Oversampling {
+    /// Decode an oversampling field read off the wire, failing on values that don't fit its 3 bits.
+    pub fn try_from_field(value: u8) -> Result<Oversampling, InvalidFieldValue> {
+        checked_field(value, 0x07).map(Oversampling::from_field)
+    }
+}
+
+impl From<u8> for Oversampling {
+    /// Decode an oversampling field, bits above its 3 bits are ignored. See `try_from_field` for
+    /// a conversion that rejects them.
+    fn from(value: u8) -> Self {
+        Oversampling::from_field(value)
+    }
+}
+
 impl From<Oversampling> for u8 {
     /// Convert from Oversampling to u8.
     fn from(value: Oversampling) -> u8 {
@@ -86,9 +211,22 @@ impl From<Oversampling> for u8 {
     }
 }
 
+impl fmt::Display for Oversampling {
+    /// Skipped, or the factor as in "16x".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Oversampling::Skipped => f.write_str("Skipped"),
+            _ => write!(f, "{}x", self.factor()),
+        }
+    }
+}
+
 
-/// Stanby time for the sensor.
-pub enum StandyTime {
+/// Standby time for the sensor. Defaults to 0.5ms, the power-on state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StandbyTime {
+    #[default]
     Ms0_5,
     Ms62_5,
     Ms125,
@@ -99,41 +237,89 @@ pub enum StandyTime {
     Ms20
 }
 
-impl From<u8> for StandyTime {
-    /// Convert from u8 to StandbyTime.
+/// Former, misspelled name of `StandbyTime`.
+#[deprecated(note = "renamed to `StandbyTime`")]
+pub type StandyTime = StandbyTime;
+
+impl StandbyTime {
+    /// Inactive time between two measurements in normal mode, in microseconds.
+    pub fn micros(&self) -> u32 {
+        match self {
+            StandbyTime::Ms0_5 => 500,
+            StandbyTime::Ms62_5 => 62_500,
+            StandbyTime::Ms125 => 125_000,
+            StandbyTime::Ms250 => 250_000,
+            StandbyTime::Ms500 => 500_000,
+            StandbyTime::Ms1000 => 1_000_000,
+            StandbyTime::Ms10 => 10_000,
+            StandbyTime::Ms20 => 20_000,
+        }
+    }
+}
+
+impl StandbyTime {
+    /// Decode the 3-bit standby field, bits above it are ignored.
+    pub(crate) fn from_field(bits: u8) -> StandbyTime {
+        match bits & 0x07 {
+            0 => StandbyTime::Ms0_5,
+            1 => StandbyTime::Ms62_5,
+            2 => StandbyTime::Ms125,
+            3 => StandbyTime::Ms250,
+            4 => StandbyTime::Ms500,
+            5 => StandbyTime::Ms1000,
+            6 => StandbyTime::Ms10,
+            _ => StandbyTime::Ms20,
+        }
+    }
+}
+
+impl StandbyTime {
+    /// Decode a standby field read off the wire, failing on values that don't fit its 3 bits.
+    pub fn try_from_field(value: u8) -> Result<StandbyTime, InvalidFieldValue> {
+        checked_field(value, 0x07).map(StandbyTime::from_field)
+    }
+}
+
+impl From<u8> for StandbyTime {
+    /// Decode a standby field, bits above its 3 bits are ignored. See `try_from_field` for
+    /// a conversion that rejects them.
     fn from(value: u8) -> Self {
+        StandbyTime::from_field(value)
+    }
+}
+
+impl From<StandbyTime> for u8 {
+    fn from(value: StandbyTime) -> u8 {
         match value {
-            0 => StandyTime::Ms0_5,
-            1 => StandyTime::Ms62_5,
-            2 => StandyTime::Ms125,
-            3 => StandyTime::Ms250,
-            4 => StandyTime::Ms500,
-            5 => StandyTime::Ms1000,
-            6 => StandyTime::Ms10,
-            7 => StandyTime::Ms20,
-            _ => panic!("Invalid standby value")
+             StandbyTime::Ms0_5 => 0,
+             StandbyTime::Ms62_5 => 1,
+             StandbyTime::Ms125 => 2,
+             StandbyTime::Ms250 => 3,
+             StandbyTime::Ms500 => 4,
+             StandbyTime::Ms1000 => 5,
+             StandbyTime::Ms10 => 6,
+             StandbyTime::Ms20 => 7
         }
     }
 }
 
-impl From<StandyTime> for u8 {
-    fn from(value: StandyTime) -> u8 {
-        match value {
-             StandyTime::Ms0_5 => 0,
-             StandyTime::Ms62_5 => 1,
-             StandyTime::Ms125 => 2,
-             StandyTime::Ms250 => 3,
-             StandyTime::Ms500 => 4,
-             StandyTime::Ms1000 => 5,
-             StandyTime::Ms10 => 6,
-             StandyTime::Ms20 => 7
+impl fmt::Display for StandbyTime {
+    /// Time in milliseconds, as in "62.5ms".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let micros = self.micros();
+        match micros % 1000 {
+            0 => write!(f, "{}ms", micros / 1000),
+            rest => write!(f, "{}.{}ms", micros / 1000, rest / 100),
         }
     }
 }
 
 
-/// Filter for sensor.
+/// Filter for sensor. Defaults to off, the power-on state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Filter {
+    #[default]
     Off,
     C2,
     C4,
@@ -141,10 +327,23 @@ pub enum Filter {
     C16
 }
 
-impl From<u8> for Filter {
-    /// Convert u8 to Filter. Expects 3 bits only.
-    fn from(value: u8) -> Self {
-    match value & 0x7 {
+impl Filter {
+    /// Samples for the filter output to reach 75% of a step, per the datasheet.
+    pub fn settling_samples(&self) -> u32 {
+        match self {
+            Filter::Off => 1,
+            Filter::C2 => 2,
+            Filter::C4 => 5,
+            Filter::C8 => 11,
+            Filter::C16 => 22,
+        }
+    }
+}
+
+impl Filter {
+    /// Decode the 3-bit filter field, bits above it are ignored. Codes above 3 all mean 16.
+    pub(crate) fn from_field(bits: u8) -> Filter {
+        match bits & 0x07 {
             0 => Filter::Off,
             1 => Filter::C2,
             2 => Filter::C4,
@@ -154,6 +353,21 @@ impl From<u8> for Filter {
     }
 }
 
+impl Filter {
+    /// Decode a filter field read off the wire, failing on values that don't fit its 3 bits.
+    pub fn try_from_field(value: u8) -> Result<Filter, InvalidFieldValue> {
+        checked_field(value, 0x07).map(Filter::from_field)
+    }
+}
+
+impl From<u8> for Filter {
+    /// Decode a filter field, bits above its 3 bits are ignored. See `try_from_field` for
+    /// a conversion that rejects them.
+    fn from(value: u8) -> Self {
+        Filter::from_field(value)
+    }
+}
+
 impl From<Filter> for u8 {
     /// Convert Filter to u8.
     fn from(value: Filter) -> u8 {
@@ -167,11 +381,84 @@ impl From<Filter> for u8 {
     }
 }
 
+impl fmt::Display for Filter {
+    /// Off, or the coefficient as in "16".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Filter::Off => "Off",
+            Filter::C2 => "2",
+            Filter::C4 => "4",
+            Filter::C8 => "8",
+            Filter::C16 => "16",
+        };
+        f.write_str(name)
+    }
+}
+
+
+/// Flags of the status register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Status {
+    /// A conversion is running, bit 3.
+    pub measuring: bool,
+    /// NVM data is being copied to the image registers, bit 0.
+    pub im_update: bool,
+}
+
+impl From<u8> for Status {
+    /// Decode the status register.
+    fn from(value: u8) -> Status {
+        Status {
+            measuring: value & 0x08 != 0,
+            im_update: value & 0x01 != 0,
+        }
+    }
+}
+
+
+/// Number of registers in a `RegisterDump`.
+pub const REGISTER_DUMP_LENGTH: usize = 13;
+
+/// Raw copy of the chip ID, control, status and data registers, for hardware bug reports.
+///
+/// `Debug` prints every register next to its address.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegisterDump(pub [u8; REGISTER_DUMP_LENGTH]);
+
+impl RegisterDump {
+    /// Address of every dumped register, in the order of the bytes.
+    pub const ADDRESSES: [u8; REGISTER_DUMP_LENGTH] = [
+        0xD0, 0xF2, 0xF3, 0xF4, 0xF5, 0xF7, 0xF8, 0xF9, 0xFA, 0xFB, 0xFC, 0xFD, 0xFE,
+    ];
+
+    /// Value of `register`, `None` when it isn't part of the dump.
+    pub fn get(&self, register: u8) -> Option<u8> {
+        let index = Self::ADDRESSES.iter().position(|&address| address == register)?;
+        Some(self.0[index])
+    }
+}
+
+impl fmt::Debug for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (address, value) in Self::ADDRESSES.iter().zip(self.0) {
+            map.entry(&format_args!("{:#04x}", address), &format_args!("{:#04x}", value));
+        }
+        map.finish()
+    }
+}
+
 
 /// Address options for the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Address {
     Default,
-    Alternative
+    Alternative,
+    /// Any 7-bit address, e.g. behind a mux or an address translator.
+    Custom(u8)
 }
 
 impl From<Address> for u8 {
@@ -179,7 +466,8 @@ impl From<Address> for u8 {
     fn from(value: Address) -> u8 {
         match value {
             Address::Default => addresses::DEFAULT,
-            Address::Alternative => addresses::ALTERNATIVE
+            Address::Alternative => addresses::ALTERNATIVE,
+            Address::Custom(address) => address
         }
     }
 }
@@ -188,7 +476,7 @@ impl From<Address> for u8 {
 /// A wrapper for the I2C device and adress to represent the sensor
 pub struct AtmosphericSensorI2c<I2C> {
     i2c: I2C,
-    address: u8
+    address: u8,
 }
 
 impl<I2C: I2c> AtmosphericSensorI2c<I2C> {
@@ -197,320 +485,588 @@ impl<I2C: I2c> AtmosphericSensorI2c<I2C> {
         AtmosphericSensorI2c { i2c, address }
     }
 
+    /// Give the I2C bus back.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Underlying I2C bus.
+    pub fn bus_mut(&mut self) -> &mut I2C {
+        &mut self.i2c
+    }
+}
+
+impl<I2C: I2c> Bus for AtmosphericSensorI2c<I2C> {
+    type Error = I2C::Error;
+
+    /// By default the register address and the read share one `write_read`, with a repeated
+    /// start in between. With the `split-write-read` feature they are sent as a `write`
+    /// followed by a separate `read`, for HALs that lack a usable `write_read`. The BME280
+    /// keeps the register pointer across the stop condition so this works, but another master
+    /// on the bus could slip in between the two transfers.
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), I2C::Error> {
+        #[cfg(not(feature = "split-write-read"))]
+        return self.i2c.write_read(self.address, &[register], buffer);
+        #[cfg(feature = "split-write-read")]
+        return self.i2c.write(self.address, &[register])
+            .and_then(|_| self.i2c.read(self.address, buffer));
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), I2C::Error> {
+        self.i2c.write(self.address, &[register, value])
+    }
+}
+
+
+/// Register map of the sensor, on top of any bus.
+pub trait Registers: Bus + Sized {
     /// Read the ID of the chip.
-    pub fn get_id(&mut self) -> u8 {
+    fn get_id(&mut self) -> Result<u8, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CHIP_ID_REG, &mut buffer).unwrap();
-        *buffer.first().unwrap()
+        read_from_register(self, Register::ChipId, &mut buffer)?;
+        Ok(buffer[0])
     }
 
     /// Reset sensor.
-    pub fn reset(&mut self) {
-        write_to_register(self, registers::RST_REG, &[values::SOFT_RESET]).unwrap();
+    fn reset(&mut self) -> Result<(), AtmosphericSensorI2cError<Self::Error>> {
+        write_to_register(self, Register::Reset, values::SOFT_RESET)?;
+        Ok(())
     }
     
     /// Get the current mode of the sensor.
-    pub fn get_mode(&mut self) -> Mode {
+    fn get_mode(&mut self) -> Result<Mode, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CTRL_MEAS_REG, &mut buffer).unwrap();
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
 
         // Convert value to Mode
-        Mode::from(*buffer.first().unwrap() & 0x03)
+        Ok(Mode::from_field(buffer[0] & 0x03))
     }
     
     /// Set mode to the sensor.
-    pub fn set_mode(&mut self, mode: Mode) {
+    fn set_mode(&mut self, mode: Mode) -> Result<(), AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CTRL_MEAS_REG, &mut buffer).unwrap();
-        let old_state = *buffer.first().unwrap() & 0xFC;
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
+        let old_state = buffer[0] & 0xFC;
         let new_state = old_state | u8::from(mode);
-        write_to_register(self, registers::CTRL_MEAS_REG, &[new_state]).unwrap();
+        write_to_register(self, Register::CtrlMeas, new_state)?;
+        Ok(())
     }
 
-    /// Get measuring bit.
-    pub fn is_measuring(&mut self) -> bool {
-        // Check bit 3 is set to 1
-        ((self.get_status() & 0x04) >> 2) == 1
+    /// Put the sensor to sleep, reporting bus errors instead of panicking.
+    #[cfg(feature = "sleep-on-drop")]
+    fn sleep(&mut self) -> Result<(), AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8];
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
+        write_to_register(self, Register::CtrlMeas, buffer[0] & 0xFC)?;
+        Ok(())
     }
 
-    /// Get updating bit.
-    pub fn is_updating(&mut self) -> bool {
-        // Check bit 0 is set to 1
-        (self.get_status() & 0x01) == 1
+    /// Get status, both flags from one read.
+    fn get_status(&mut self) -> Result<Status, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8];
+        read_from_register(self, Register::Status, &mut buffer)?;
+        Ok(Status::from(buffer[0]))
     }
 
-    /// Get status.
-    fn get_status(&mut self) -> u8 {
+    /// Write oversampling for humidity sampling.
+    ///
+    /// ctrl_hum only takes effect after a write to ctrl_meas, so ctrl_meas is written back
+    /// with its current contents.
+    fn set_humidity_oversample(&mut self, rate: Oversampling) -> Result<(), AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::STAT_REG, &mut buffer).unwrap();
-        *buffer.first().unwrap()
+        read_from_register(self, Register::CtrlHumidity, &mut buffer)?;
+        write_to_register(self, Register::CtrlHumidity, (buffer[0] & 0xF8) | u8::from(rate))?;
+
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
+        write_to_register(self, Register::CtrlMeas, buffer[0])?;
+        Ok(())
     }
 
-    /// Write oversampling for humidity sampling.
-    pub fn set_humidity_oversample(&mut self, rate: Oversampling) {
+    /// Write oversampling for pressure sampling.
+    fn set_pressure_oversample(&mut self, rate: Oversampling) -> Result<(), AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CTRL_HUMIDITY_REG, &mut buffer).unwrap();
-    
-        let old_state = *buffer.first().unwrap() & 0xF8;
-        let new_state = old_state | u8::from(rate);
-        write_to_register(self, registers::CTRL_HUMIDITY_REG, &[new_state]).unwrap();
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
+        write_to_register(self, Register::CtrlMeas, (buffer[0] & 0xE3) | (u8::from(rate) << 2))?;
+        Ok(())
     }
-    
-    /// Write oversampling for humidity sampling.
-    pub fn set_temperature_oversample(&mut self, rate: Oversampling) {
+
+    /// Get oversampling for humidity sampling.
+    fn get_humidity_oversample(&mut self) -> Result<Oversampling, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CTRL_MEAS_REG, &mut buffer).unwrap();
-    
-        let old_state = *buffer.first().unwrap() & 0x1F;
-        let new_state = old_state | (u8::from(rate) << 5);
-        write_to_register(self, registers::CTRL_MEAS_REG, &[new_state]).unwrap();
+        read_from_register(self, Register::CtrlHumidity, &mut buffer)?;
+        Ok(Oversampling::from_field(buffer[0] & 0x07))
     }
-    
-    /// Write oversampling for pressure sampling.
-    pub fn set_pressure_oversample(&mut self, rate: Oversampling) {
+
+    /// Get oversampling for temperature sampling.
+    fn get_temperature_oversample(&mut self) -> Result<Oversampling, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CTRL_MEAS_REG, &mut buffer).unwrap();
-        let old_state = *buffer.first().unwrap() & 0xE3;
-        let new_state = old_state | (u8::from(rate) << 2);
-        write_to_register(self, registers::CTRL_MEAS_REG, &[new_state]).unwrap();
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
+        Ok(Oversampling::from_field(buffer[0] >> 5))
     }
-    
-    /// Set stamby time to sensor.
-    pub fn set_standby_time(&mut self, standby: StandyTime) {
+
+    /// Get oversampling for pressure sampling.
+    fn get_pressure_oversample(&mut self) -> Result<Oversampling, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CONFIG_REG, &mut buffer).unwrap();
-        let old_state = *buffer.first().unwrap() & 0x1F;
-        let new_state = old_state | (u8::from(standby) << 5);
-        write_to_register(self, registers::CONFIG_REG, &[new_state]).unwrap();
+        read_from_register(self, Register::CtrlMeas, &mut buffer)?;
+        Ok(Oversampling::from_field((buffer[0] >> 2) & 0x07))
     }
-    
-    /// Set filter to sensor.
-    pub fn set_filter(&mut self, filter: Filter) {
+
+    /// Get standby time of the sensor.
+    fn get_standby_time(&mut self) -> Result<StandbyTime, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8];
+        read_from_register(self, Register::Config, &mut buffer)?;
+        Ok(StandbyTime::from_field(buffer[0] >> 5))
+    }
+
+    /// Get filter of the sensor.
+    fn get_filter(&mut self) -> Result<Filter, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer = [0u8];
-        read_from_register(self, registers::CONFIG_REG, &mut buffer).unwrap();
-        let old_state = *buffer.first().unwrap() & 0xE3;
-        let new_state = old_state | (u8::from(filter) << 2);
-        write_to_register(self, registers::CONFIG_REG, &[new_state]).unwrap();
+        read_from_register(self, Register::Config, &mut buffer)?;
+        Ok(Filter::from_field((buffer[0] >> 2) & 0x07))
     }
     
     /// Get temperature value from sensor.
-    pub fn get_temperature_raw(&mut self) -> u32 {
+    fn get_temperature_raw(&mut self) -> Result<u32, AtmosphericSensorI2cError<Self::Error>> {
+        // MSB, LSB and XLSB in one transaction, so they belong to the same conversion
         let mut buffer = [0u8; 3];
-        read_from_register(self, registers::TEMPERATURE_MSB_REG, &mut buffer[0..1]).unwrap();
-        read_from_register(self, registers::TEMPERATURE_LSB_REG, &mut buffer[1..2]).unwrap();
-        read_from_register(self, registers::TEMPERATURE_XLSB_REG, &mut buffer[2..3]).unwrap();
+        read_from_register(self, Register::TemperatureMsb, &mut buffer)?;
     
-        (u32::from(buffer[0]) << 12) | (u32::from(buffer[1]) << 4) | ((u32::from(buffer[2]) >> 4) & 0x0F)
+        Ok((u32::from(buffer[0]) << 12) | (u32::from(buffer[1]) << 4) | ((u32::from(buffer[2]) >> 4) & 0x0F))
     }
     
     /// Get pressure value from sensor.
-    pub fn get_pressure_raw(&mut self) -> u32 {
+    fn get_pressure_raw(&mut self) -> Result<u32, AtmosphericSensorI2cError<Self::Error>> {
+        // MSB, LSB and XLSB in one transaction, so they belong to the same conversion
         let mut buffer = [0u8; 3];
-        read_from_register(self, registers::PRESSURE_MSB_REG, &mut buffer[0..1]).unwrap();
-        read_from_register(self, registers::PRESSURE_LSB_REG, &mut buffer[1..2]).unwrap();
-        read_from_register(self, registers::PRESSURE_XLSB_REG, &mut buffer[2..3]).unwrap();
+        read_from_register(self, Register::PressureMsb, &mut buffer)?;
     
-        (u32::from(buffer[0]) << 12) | (u32::from(buffer[1]) << 4) | ((u32::from(buffer[2]) >> 4) & 0x0F)
+        Ok((u32::from(buffer[0]) << 12) | (u32::from(buffer[1]) << 4) | ((u32::from(buffer[2]) >> 4) & 0x0F))
     }
     
     /// Get humidity value from sensor.
-    pub fn get_humidity_raw(&mut self) -> u32 {
+    fn get_humidity_raw(&mut self) -> Result<u32, AtmosphericSensorI2cError<Self::Error>> {
+        // MSB and LSB in one transaction, so they belong to the same conversion
         let mut buffer = [0u8; 2];
-        read_from_register(self, registers::HUMIDITY_MSB_REG, &mut buffer[0..1]).unwrap();
-        read_from_register(self, registers::HUMIDITY_LSB_REG, &mut buffer[1..2]).unwrap();
+        read_from_register(self, Register::HumidityMsb, &mut buffer)?;
     
-        (u32::from(buffer[0]) << 8) | (u32::from(buffer[1]))
+        Ok((u32::from(buffer[0]) << 8) | (u32::from(buffer[1])))
+    }
+
+    /// Read ctrl_hum, ctrl_meas and config in one transaction.
+    fn read_control_registers(&mut self) -> Result<(u8, u8, u8), AtmosphericSensorI2cError<Self::Error>> {
+        // ctrl_hum, status, ctrl_meas and config are consecutive
+        let mut buffer = [0u8; 4];
+        read_from_register(self, Register::CtrlHumidity, &mut buffer)?;
+        Ok((buffer[0], buffer[2], buffer[3]))
+    }
+
+    /// Read the chip ID, then the control and status registers and the data registers in one
+    /// transaction each.
+    fn dump_registers(&mut self) -> Result<RegisterDump, AtmosphericSensorI2cError<Self::Error>> {
+        let mut dump = [0u8; REGISTER_DUMP_LENGTH];
+        let (id, rest) = dump.split_at_mut(1);
+        let (control, data) = rest.split_at_mut(4);
+        read_from_register(self, Register::ChipId, id)?;
+        read_from_register(self, Register::CtrlHumidity, control)?;
+        read_from_register(self, Register::PressureMsb, data)?;
+        Ok(RegisterDump(dump))
+    }
+
+    /// Read all data registers, from pressure MSB to humidity LSB, in one transaction.
+    fn read_burst(&mut self) -> Result<[u8; DATA_FRAME_LENGTH], AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; DATA_FRAME_LENGTH];
+        read_from_register(self, Register::PressureMsb, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Read raw temperature and humidity, from temperature MSB to humidity LSB, in one
+    /// transaction.
+    fn read_temperature_humidity_raw(&mut self) -> Result<(u32, u32), AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 5];
+        read_from_register(self, Register::TemperatureMsb, &mut buffer)?;
+        let temperature = (u32::from(buffer[0]) << 12) | (u32::from(buffer[1]) << 4) | (u32::from(buffer[2]) >> 4);
+        let humidity = (u32::from(buffer[3]) << 8) | u32::from(buffer[4]);
+        Ok((temperature, humidity))
     }
 
     /// Get T1 value for temperature calibration.
-    pub fn get_t1(&mut self) -> u16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_t1(&mut self) -> Result<u16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_T1_LSB_REG,
             registers::DIG_T1_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_u16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_u16(&buffer))
     }
 
     /// Get T2 value for temperature calibration.
-    pub fn get_t2(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_t2(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_T2_LSB_REG,
             registers::DIG_T2_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get T3 value for temperature calibration.
-    pub fn get_t3(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_t3(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_T3_LSB_REG,
             registers::DIG_T3_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P1 value for pressure calibration.
-    pub fn get_p1(&mut self) -> u16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p1(&mut self) -> Result<u16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P1_LSB_REG,
             registers::DIG_P1_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_u16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_u16(&buffer))
     }
 
     /// Get P2 value for pressure calibration.
-    pub fn get_p2(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p2(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P2_LSB_REG,
             registers::DIG_P2_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P3 value for pressure calibration.
-    pub fn get_p3(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p3(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P3_LSB_REG,
             registers::DIG_P3_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P4 value for pressure calibration.
-    pub fn get_p4(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p4(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P4_LSB_REG,
             registers::DIG_P4_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P5 value for pressure calibration.
-    pub fn get_p5(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p5(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P5_LSB_REG,
             registers::DIG_P5_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P6 value for pressure calibration.
-    pub fn get_p6(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p6(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P6_LSB_REG,
             registers::DIG_P6_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P7 value for pressure calibration.
-    pub fn get_p7(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p7(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P7_LSB_REG,
             registers::DIG_P7_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P8 value for pressure calibration.
-    pub fn get_p8(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p8(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P8_LSB_REG,
             registers::DIG_P8_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get P9 value for pressure calibration.
-    pub fn get_p9(&mut self) -> i16 {
-        let buffer = read_multiple_registers(self, &[
+    fn get_p9(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_P9_LSB_REG,
             registers::DIG_P9_MSB_REG
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get H1 value for humidity calibration.
-    pub fn get_h1(&mut self) -> u8 {
-        let mut buffer = read_multiple_registers(self, &[registers::DIG_H1_REG]).unwrap();
-        buffer.pop().unwrap()
+    fn get_h1(&mut self) -> Result<u8, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8];
+        read_from_register(self, Register::DigH1, &mut buffer)?;
+        Ok(buffer[0])
     }
 
     /// Get H2 value for humidity calibration.
-    pub fn get_h2(&mut self) -> i16 {
-        let buffer: Vec<u8> = read_multiple_registers(self, &[
+    fn get_h2(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8; 2];
+        read_multiple_registers(self, &[
             registers::DIG_H2_LSB_REG,
             registers::DIG_H2_MSB_REG,
-        ]).unwrap();
-        LittleEndian::read_i16(&buffer)
+        ], &mut buffer)?;
+        Ok(LittleEndian::read_i16(&buffer))
     }
 
     /// Get H3 value for humidity calibration.
-    pub fn get_h3(&mut self) -> u8 {
-        let mut buffer = read_multiple_registers(self, &[registers::DIG_H3_REG]).unwrap();
-        buffer.pop().unwrap()
+    fn get_h3(&mut self) -> Result<u8, AtmosphericSensorI2cError<Self::Error>> {
+        let mut buffer = [0u8];
+        read_from_register(self, Register::DigH3, &mut buffer)?;
+        Ok(buffer[0])
     }
 
     /// Get H4 value for humidity calibration.
-    pub fn get_h4(&mut self) -> i16 {
+    fn get_h4(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer  = [0u8; 2];
-        read_from_register(self, registers::DIG_H4_MSB_REG, &mut buffer[0..1]).unwrap();
-        read_from_register(self, registers::DIG_H4_LSB_REG, &mut buffer[1..2]).unwrap();
+        read_from_register(self, Register::DigH4Msb, &mut buffer[0..1])?;
+        read_from_register(self, Register::DigH4H5, &mut buffer[1..2])?;
 
-        ((u16::from(buffer[0]) << 4) | (u16::from(buffer[1]) & 0x0F)) as i16
+        // 12-bit signed, the MSB carries the sign
+        Ok((i16::from(buffer[0] as i8) << 4) | i16::from(buffer[1] & 0x0F))
     }
 
     /// Get H5 value for humidity calibration.
-    pub fn get_h5(&mut self) -> i16 {
+    fn get_h5(&mut self) -> Result<i16, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer  = [0u8; 2];
-        read_from_register(self, registers::DIG_H5_MSB_REG, &mut buffer[0..1]).unwrap();
-        read_from_register(self, registers::DIG_H4_LSB_REG, &mut buffer[1..2]).unwrap();
+        read_from_register(self, Register::DigH5Msb, &mut buffer[0..1])?;
+        read_from_register(self, Register::DigH4H5, &mut buffer[1..2])?;
 
-        (((u16::from(buffer[0]) << 4)) | ((u16::from(buffer[1]) >> 4) & 0x0F)) as i16
+        // 12-bit signed, the MSB carries the sign
+        Ok((i16::from(buffer[0] as i8) << 4) | i16::from(buffer[1] >> 4))
     }
 
     /// Get H6 value for humidity calibration.
-    pub fn get_h6(&mut self) -> i8 {
+    fn get_h6(&mut self) -> Result<i8, AtmosphericSensorI2cError<Self::Error>> {
         let mut buffer  = [0u8; 1];
-        read_from_register(self, registers::DIG_H6_REG, &mut buffer).unwrap();
+        read_from_register(self, Register::DigH6, &mut buffer)?;
 
-        buffer[0] as i8
+        Ok(buffer[0] as i8)
     }
 
 }
 
+impl<B: Bus> Registers for B {}
+
 
 /// Get value from a specific register in sensor.
-pub fn read_from_register<I2C: I2c>(dev: &mut AtmosphericSensorI2c<I2C> , register: u8, buffer: &mut [u8]) -> Result<(), AtmosphericSensorI2cError> {
-    match dev.i2c.write_read(dev.address, &[register], buffer) {
-        Ok(_) => Ok(()),
-        Err(_) => Err(AtmosphericSensorI2cError::IOError)
-    }
+///
+/// The bus error is returned as is, so callers can match on it or on its `ErrorKind`.
+pub fn read_from_register<B: Bus>(dev: &mut B, register: Register, buffer: &mut [u8]) -> Result<(), B::Error> {
+    dev.read_register(register.address(), buffer)
 }
 
 /// Set value from a specific register in sensor.
-pub fn write_to_register<I2C: I2c>(dev: &mut AtmosphericSensorI2c<I2C>, register: u8, bytes: &[u8]) -> Result<(), AtmosphericSensorI2cError> {
-    let mut buffer = Vec::<u8>::with_capacity(1+bytes.len());
-    buffer.push(register);
-    for value in bytes {
-        buffer.push(*value);
-    }
-    // TODO check if it matches write_bytes
-    match dev.i2c.write(dev.address, &buffer) {
-        Ok(_) => Ok(()),
-        Err(_) => Err(AtmosphericSensorI2cError::IOError)
-    }
-}
-
-/// Helper function to read multiple registers at once and store value on Vec.
-fn read_multiple_registers<I2C: I2c>(dev: &mut AtmosphericSensorI2c<I2C>, registers: &[u8]) -> Result<Vec<u8>, AtmosphericSensorI2cError> {
-    let mut buffer: Vec<u8> = vec![];
-    for register in registers.iter() {
-        let mut temp_buffer  = [0u8];
-        match read_from_register(dev, *register, &mut temp_buffer) {
-            Ok(_) => buffer.extend(temp_buffer),
-            Err(_) => {return Err(AtmosphericSensorI2cError::IOError)}
+///
+/// The sensor takes one register-value pair per write, so a single byte is written.
+pub fn write_to_register<B: Bus>(dev: &mut B, register: Register, value: u8) -> Result<(), B::Error> {
+    debug_assert!(register.is_writable(), "{:?} is read-only", register);
+    dev.write_register(register.address(), value)
+}
+
+/// Helper function to read registers one by one into `buffer`, one byte per register.
+fn read_multiple_registers<B: Bus>(dev: &mut B, registers: &[u8], buffer: &mut [u8]) -> Result<(), AtmosphericSensorI2cError<B::Error>> {
+    for (register, byte) in registers.iter().zip(buffer.iter_mut()) {
+        dev.read_register(*register, core::slice::from_mut(byte))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_map() {
+        assert_eq!(u8::from(Register::CtrlMeas), 0xF4);
+        // H4 and H5 share one register
+        assert_eq!(registers::DIG_H4_LSB_REG, registers::DIG_H5_LSB_REG);
+        assert!(Register::Config.is_writable());
+        assert!(!Register::Status.is_writable());
+        assert!(!Register::ChipId.is_writable());
+    }
+
+    #[test]
+    fn oversampling_round_trip() {
+        let expected = [
+            (Oversampling::Skipped, 0b000),
+            (Oversampling::Ox1, 0b001),
+            (Oversampling::Ox2, 0b010),
+            (Oversampling::Ox4, 0b011),
+            (Oversampling::Ox8, 0b100),
+            (Oversampling::Ox16, 0b101),
+        ];
+        for (rate, bits) in expected {
+            assert_eq!(u8::from(rate), bits);
+            assert_eq!(Oversampling::try_from_field(bits), Ok(rate));
+        }
+        // Remaining codes are 16x as well
+        assert_eq!(Oversampling::try_from_field(0b110), Ok(Oversampling::Ox16));
+        assert_eq!(Oversampling::try_from_field(0b111), Ok(Oversampling::Ox16));
+        assert_eq!(Oversampling::try_from_field(0b1000), Err(InvalidFieldValue(0b1000)));
+    }
+
+    #[test]
+    fn mode_from_bits() {
+        assert_eq!(Mode::try_from_field(0), Ok(Mode::Sleep));
+        assert_eq!(Mode::try_from_field(1), Ok(Mode::Forced));
+        assert_eq!(Mode::try_from_field(2), Ok(Mode::Forced));
+        assert_eq!(Mode::try_from_field(3), Ok(Mode::Normal));
+        assert_eq!(Mode::try_from_field(4), Err(InvalidFieldValue(4)));
+        // Both forced codes encode back to the same forced code
+        assert_eq!(u8::from(Mode::try_from_field(2).unwrap()), 1);
+        for mode in [Mode::Sleep, Mode::Forced, Mode::Normal] {
+            assert_eq!(Mode::try_from_field(u8::from(mode)), Ok(mode));
         }
     }
-    Ok(buffer)
+
+    #[test]
+    fn standby_time_round_trip() {
+        let expected = [
+            (StandbyTime::Ms0_5, 0b000),
+            (StandbyTime::Ms62_5, 0b001),
+            (StandbyTime::Ms125, 0b010),
+            (StandbyTime::Ms250, 0b011),
+            (StandbyTime::Ms500, 0b100),
+            (StandbyTime::Ms1000, 0b101),
+            (StandbyTime::Ms10, 0b110),
+            (StandbyTime::Ms20, 0b111),
+        ];
+        for (standby, bits) in expected {
+            assert_eq!(u8::from(standby), bits);
+            assert_eq!(StandbyTime::try_from_field(bits), Ok(standby));
+        }
+        assert_eq!(StandbyTime::try_from_field(8), Err(InvalidFieldValue(8)));
+    }
+
+    #[test]
+    fn filter_round_trip() {
+        let expected = [
+            (Filter::Off, 0b000),
+            (Filter::C2, 0b001),
+            (Filter::C4, 0b010),
+            (Filter::C8, 0b011),
+            (Filter::C16, 0b100),
+        ];
+        for (filter, bits) in expected {
+            assert_eq!(u8::from(filter), bits);
+            assert_eq!(Filter::try_from_field(bits), Ok(filter));
+        }
+        // Remaining codes are filter coefficient 16 as well
+        assert_eq!(Filter::try_from_field(0b101), Ok(Filter::C16));
+        assert_eq!(Filter::try_from_field(0b111), Ok(Filter::C16));
+        assert_eq!(Filter::try_from_field(0xFF), Err(InvalidFieldValue(0xFF)));
+        // Register decoding masks the field instead
+        assert_eq!(Filter::from_field(0b1001), Filter::C2);
+    }
+
+    #[test]
+    fn from_u8_masks_the_field() {
+        assert_eq!(Mode::from(0b111), Mode::Normal);
+        assert_eq!(Oversampling::from(0b1001), Oversampling::Ox1);
+        assert_eq!(StandbyTime::from(0xFF), StandbyTime::Ms20);
+        assert_eq!(Filter::from(0b1010), Filter::C4);
+    }
+
+    #[test]
+    #[cfg(feature = "split-write-read")]
+    fn split_write_read() {
+        use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+        let expectations = [
+            I2cTransaction::write(addresses::DEFAULT, vec![registers::CHIP_ID_REG]),
+            I2cTransaction::read(addresses::DEFAULT, vec![values::CHIP_ID_BME280]),
+        ];
+        let i2c = I2cMock::new(&expectations);
+        let mut i2c_clone = i2c.clone();
+
+        let mut dev = AtmosphericSensorI2c::new(i2c, addresses::DEFAULT);
+        assert_eq!(dev.get_id().unwrap(), values::CHIP_ID_BME280);
+
+        i2c_clone.done();
+    }
+
+    #[test]
+    fn decode_signed_h4_h5() {
+        use embedded_hal_mock::eh1::i2c::Mock as I2cMock;
+        use crate::tests::{register_read, transactions};
+
+        // H4 = -312 and H5 = -51, sharing 0xD8 in 0xE5
+        let expectations = transactions![
+            register_read(addresses::DEFAULT, vec![registers::DIG_H4_MSB_REG], vec![0xEC]),
+            register_read(addresses::DEFAULT, vec![registers::DIG_H4_LSB_REG], vec![0xD8]),
+            register_read(addresses::DEFAULT, vec![registers::DIG_H5_MSB_REG], vec![0xFC]),
+            register_read(addresses::DEFAULT, vec![registers::DIG_H5_LSB_REG], vec![0xD8]),
+        ];
+        let i2c = I2cMock::new(&expectations);
+        let mut i2c_clone = i2c.clone();
+
+        let mut dev = AtmosphericSensorI2c::new(i2c, addresses::DEFAULT);
+        assert_eq!(dev.get_h4().unwrap(), -312);
+        assert_eq!(dev.get_h5().unwrap(), -51);
+
+        i2c_clone.done();
+    }
+
+    #[test]
+    fn decode_status() {
+        assert_eq!(Status::from(0x00), Status { measuring: false, im_update: false });
+        // measuring is bit 3, im_update bit 0
+        assert_eq!(Status::from(0x08), Status { measuring: true, im_update: false });
+        assert_eq!(Status::from(0x01), Status { measuring: false, im_update: true });
+        assert_eq!(Status::from(0x09), Status { measuring: true, im_update: true });
+        // Reserved bits are ignored
+        assert_eq!(Status::from(0xF6), Status { measuring: false, im_update: false });
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Mode::Normal.to_string(), "Normal");
+        assert_eq!(Oversampling::Skipped.to_string(), "Skipped");
+        assert_eq!(Oversampling::Ox16.to_string(), "16x");
+        assert_eq!(StandbyTime::Ms0_5.to_string(), "0.5ms");
+        assert_eq!(StandbyTime::Ms62_5.to_string(), "62.5ms");
+        assert_eq!(StandbyTime::Ms1000.to_string(), "1000ms");
+        assert_eq!(Filter::Off.to_string(), "Off");
+        assert_eq!(Filter::C16.to_string(), "16");
+    }
+
+    #[test]
+    fn encoded_values_fit_their_fields() {
+        // Every setting is shifted into a 3-bit field of its register.
+        for bits in 0..8u8 {
+            assert!(u8::from(Oversampling::from_field(bits)) <= 0x7);
+            assert!(u8::from(StandbyTime::from_field(bits)) <= 0x7);
+            assert!(u8::from(Filter::from_field(bits)) <= 0x7);
+        }
+    }
+
+    #[test]
+    fn variant_from_chip_id() {
+        assert_eq!(Variant::from_chip_id(values::CHIP_ID_BME280), Some(Variant::Bme280));
+        assert_eq!(Variant::from_chip_id(values::CHIP_ID_BMP280), Some(Variant::Bmp280));
+        assert_eq!(Variant::from_chip_id(values::CHIP_ID_BMP280_SAMPLE_1), Some(Variant::Bmp280));
+        assert_eq!(Variant::from_chip_id(values::CHIP_ID_BMP280_SAMPLE_2), Some(Variant::Bmp280));
+        // BME680
+        assert_eq!(Variant::from_chip_id(0x61), None);
+        assert!(Variant::Bme280.has_humidity());
+        assert!(!Variant::Bmp280.has_humidity());
+    }
 }
@@ -1,9 +1,64 @@
+#[allow(dead_code)]
 pub mod values {
     pub const SOFT_RESET: u8 = 0xB6;
-    pub const CHIP_ID: u8 = 0x60;
+    /// Chip ID of the BME280.
+    pub const CHIP_ID_BME280: u8 = 0x60;
+    /// Chip ID of mass production BMP280.
+    pub const CHIP_ID_BMP280: u8 = 0x58;
+    /// Chip IDs reported by BMP280 engineering samples.
+    pub const CHIP_ID_BMP280_SAMPLE_1: u8 = 0x56;
+    pub const CHIP_ID_BMP280_SAMPLE_2: u8 = 0x57;
 }
 
+/// Registers addressed on their own, rather than as part of a calibration burst.
+///
+/// Each address appears once, so a register shared by two fields, like H4 and H5 in 0xE5,
+/// can't be given two different addresses by mistake.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    DigH1 = 0xA1,
+    ChipId = 0xD0,
+    Reset = 0xE0,
+    DigH2Lsb = 0xE1,
+    DigH3 = 0xE3,
+    DigH4Msb = 0xE4,
+    /// H4 in the low nibble, H5 in the high one.
+    DigH4H5 = 0xE5,
+    DigH5Msb = 0xE6,
+    DigH6 = 0xE7,
+    CtrlHumidity = 0xF2,
+    Status = 0xF3,
+    CtrlMeas = 0xF4,
+    Config = 0xF5,
+    /// Start of the data burst.
+    PressureMsb = 0xF7,
+    TemperatureMsb = 0xFA,
+    HumidityMsb = 0xFD,
+}
+
+impl Register {
+    /// Address on the bus.
+    pub const fn address(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the sensor accepts writes to the register.
+    pub const fn is_writable(self) -> bool {
+        matches!(self, Register::Reset | Register::CtrlHumidity | Register::CtrlMeas | Register::Config)
+    }
+}
+
+impl From<Register> for u8 {
+    fn from(register: Register) -> u8 {
+        register.address()
+    }
+}
+
+#[allow(dead_code)]
 pub mod registers {
+    use super::Register;
+
     pub const DIG_T1_LSB_REG: u8 = 0x88;
     pub const DIG_T1_MSB_REG: u8 = 0x89;
     pub const DIG_T2_LSB_REG: u8 = 0x8A;
@@ -30,32 +85,34 @@ pub mod registers {
     pub const DIG_P9_LSB_REG: u8 = 0x9E;
     pub const DIG_P9_MSB_REG: u8 = 0x9F;
 
-    pub const DIG_H1_REG: u8 = 0xA1;
-    pub const DIG_H2_LSB_REG: u8 = 0xE1;
+    pub const DIG_H1_REG: u8 = Register::DigH1.address();
+    pub const DIG_H2_LSB_REG: u8 = Register::DigH2Lsb.address();
     pub const DIG_H2_MSB_REG: u8 = 0xE2;
-    pub const DIG_H3_REG: u8 = 0xE3;
-    pub const DIG_H4_MSB_REG: u8 = 0xE4;
-    pub const DIG_H4_LSB_REG: u8 = 0xE5;
-    pub const DIG_H5_MSB_REG: u8 = 0xE6;
-    pub const DIG_H6_REG: u8 = 0xE7;
+    pub const DIG_H3_REG: u8 = Register::DigH3.address();
+    pub const DIG_H4_MSB_REG: u8 = Register::DigH4Msb.address();
+    pub const DIG_H4_LSB_REG: u8 = Register::DigH4H5.address();
+    /// H4 and H5 share 0xE5, H4 in the low nibble and H5 in the high one.
+    pub const DIG_H5_LSB_REG: u8 = Register::DigH4H5.address();
+    pub const DIG_H5_MSB_REG: u8 = Register::DigH5Msb.address();
+    pub const DIG_H6_REG: u8 = Register::DigH6.address();
 
-    pub const TEMPERATURE_MSB_REG: u8 = 0xFA;  // Temperature MSB
+    pub const TEMPERATURE_MSB_REG: u8 = Register::TemperatureMsb.address();  // Temperature MSB
     pub const TEMPERATURE_LSB_REG: u8 = 0xFB;  // Temperature LSB
     pub const TEMPERATURE_XLSB_REG: u8 = 0xFC;  // Temperature XLSB
 
-    pub const PRESSURE_MSB_REG: u8 = 0xF7;  // Pressure MSB
+    pub const PRESSURE_MSB_REG: u8 = Register::PressureMsb.address();  // Pressure MSB
     pub const PRESSURE_LSB_REG: u8 = 0xF8;  // Pressure LSB
     pub const PRESSURE_XLSB_REG: u8 = 0xF9;  // Pressure XLSB
 
-    pub const HUMIDITY_MSB_REG: u8 = 0xFD;  // Humidity MSB
+    pub const HUMIDITY_MSB_REG: u8 = Register::HumidityMsb.address();  // Humidity MSB
     pub const HUMIDITY_LSB_REG: u8 = 0xFE;  // Humidity LSB
 
-    pub const CTRL_HUMIDITY_REG: u8 = 0xF2;  // Ctrl Humidity Reg
-    pub const STAT_REG: u8 = 0xF3;  // Status Reg
-    pub const CTRL_MEAS_REG: u8 = 0xF4;  // Ctrl Measure Reg
-    pub const CONFIG_REG: u8 = 0xF5;  // Configuration Reg
-    pub const CHIP_ID_REG: u8 = 0xD0;  // Chip ID
-    pub const RST_REG: u8 = 0xE0;  // Softreset Reg
+    pub const CTRL_HUMIDITY_REG: u8 = Register::CtrlHumidity.address();  // Ctrl Humidity Reg
+    pub const STAT_REG: u8 = Register::Status.address();  // Status Reg
+    pub const CTRL_MEAS_REG: u8 = Register::CtrlMeas.address();  // Ctrl Measure Reg
+    pub const CONFIG_REG: u8 = Register::Config.address();  // Configuration Reg
+    pub const CHIP_ID_REG: u8 = Register::ChipId.address();  // Chip ID
+    pub const RST_REG: u8 = Register::Reset.address();  // Softreset Reg
 
 }
 
@@ -1,238 +1,3125 @@
+// Tests run on the host, where the mock fixtures need std.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
 // Local modules
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "std")]
+mod barometer;
+mod bmp180;
+mod bus;
 mod calibration;
+mod config;
+#[cfg(feature = "std")]
+pub mod derived;
 mod i2c;
+mod liveness;
+mod measurement;
+pub mod ranges;
+mod redundancy;
+mod spi;
+mod state;
+mod stream;
+#[cfg(feature = "uom")]
+mod units;
 
 // Public imports
+use core::fmt;
+use embedded_hal::delay::DelayNs;
 use embedded_hal::i2c::I2c;
+use embedded_hal::spi::SpiDevice;
+
+// Local imports
+use i2c::{AtmosphericSensorI2cError, Registers};
+use i2c::constants::{registers, Register};
+#[cfg(feature = "async")]
+pub use asynch::AtmosphericSensorAsync;
+#[cfg(feature = "std")]
+pub use barometer::{Barometer, BarometerReading, PressureTrend};
+pub use bus::Bus;
+pub use bmp180::{Bmp180Calibration, BMP180_CALIBRATION_LENGTH};
+pub use calibration::{Calibration, CompensatedHumidity, Compensation, CompensationError, HumidityCalibration, PressureCalibration, TemperatureCalibration};
+pub use config::{Config, OutOfRange, Profile};
+pub use i2c::{Address, AtmosphericSensorI2c, Filter, InvalidFieldValue, Mode, Oversampling, RegisterDump, StandbyTime, Status, Variant, REGISTER_DUMP_LENGTH};
+#[allow(deprecated)]
+pub use i2c::StandyTime;
+pub use i2c::constants::values::{CHIP_ID_BME280, CHIP_ID_BMP280, CHIP_ID_BMP280_SAMPLE_1, CHIP_ID_BMP280_SAMPLE_2};
+#[cfg(feature = "trace")]
+pub use bus::{Direction, TraceEvent, Traced};
+pub use liveness::LivenessWindow;
+pub use measurement::{is_skipped_raw, Channel, Measurements, RawMeasurement, RawMeasurementError, Weather};
+pub use measurement::{DATA_FRAME_LENGTH, SKIPPED_HUMIDITY_RAW, SKIPPED_PRESSURE_RAW, SKIPPED_TEMPERATURE_RAW};
+pub use redundancy::{cross_check, CrossCheck, Tolerance};
+pub use spi::AtmosphericSensorSpi;
+pub use state::SensorState;
+pub use stream::MeasurementStream;
+
+/// Datasheet start-up time in microseconds, before the first conversion begins.
+const STARTUP_TIME_US: u32 = 2000;
+
+/// Number of burst reads attempted before giving up on a stable sample.
+const BURST_ATTEMPTS: usize = 3;
+
+/// Interval in microseconds between status polls while waiting for a forced measurement.
+const FORCED_POLL_INTERVAL_US: u32 = 1000;
+
+/// Number of status polls before giving up on a forced measurement, longer than the slowest
+/// configuration (16x on every channel) takes.
+const FORCED_POLL_ATTEMPTS: usize = 150;
+
+/// Millimetres of mercury per pascal.
+const MMHG_PER_PASCAL: f64 = 0.00750062;
+
+/// Inches of mercury per pascal.
+const INHG_PER_PASCAL: f64 = 0.0002953;
+
+/// Standard atmosphere at sea level in pascal, the default altitude reference.
+pub const STANDARD_SEA_LEVEL_PASCAL: f64 = 101325.0;
+
+/// Interval in microseconds between status polls while waiting for the NVM copy after a reset.
+const RESET_POLL_INTERVAL_US: u32 = 500;
+
+/// Number of status polls before giving up on the NVM copy, over twice the start-up time.
+const RESET_POLL_ATTEMPTS: usize = 10;
+
+
+/// Errors reported by the driver, carrying the error of the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SensorError<E> {
+    /// The bus reported an error.
+    I2c(E),
+    /// The chip ID matches no supported sensor.
+    InvalidChipId(u8),
+    /// The I2C address doesn't fit in 7 bits.
+    InvalidAddress(u8),
+    /// A sample taken at startup fell outside the operating ranges, see `verify_calibration`.
+    CalibrationFailed,
+    /// The calibration read from the sensor is blank, see `Calibration::is_blank`.
+    CalibrationBlank,
+    /// A forced measurement or a reset didn't complete in time.
+    Timeout,
+    /// No configuration was written or read back since construction or reset.
+    NotConfigured,
+    /// The sensor is in sleep mode.
+    Asleep,
+    /// The raw data stopped changing, see `LivenessWindow`.
+    Stuck,
+    /// The variant has no humidity channel.
+    NoHumidity,
+    /// The channel is skipped, its data registers hold the reset value: 0x80000 for
+    /// temperature and pressure, 0x8000 for humidity. Its oversampling is `Oversampling::Skipped`.
+    Skipped(Channel),
+    /// A reading fell outside the operating range with the `OutOfRange::Error` policy.
+    OutOfRange(Channel),
+    /// A control register didn't hold what was written to it.
+    ConfigMismatch { register: u8, written: u8, read: u8 },
+    /// Every burst read straddled a conversion.
+    BurstStraddled,
+    /// The compensation formulas failed.
+    Compensation(CompensationError),
+    /// The altitude is out of range of the barometric formula, 44330 meters or more.
+    AltitudeOutOfRange,
+    /// An averaged measurement was asked for 0 samples.
+    NoSamples,
+}
+
+impl<E> From<AtmosphericSensorI2cError<E>> for SensorError<E> {
+    fn from(error: AtmosphericSensorI2cError<E>) -> Self {
+        match error {
+            AtmosphericSensorI2cError::IOError(error) => SensorError::I2c(error),
+        }
+    }
+}
+
+impl<E> From<CompensationError> for SensorError<E> {
+    fn from(error: CompensationError) -> Self {
+        SensorError::Compensation(error)
+    }
+}
+
+/// Bus errors are printed with `Debug`, as most HAL error types don't implement `Display`.
+impl<E: fmt::Debug> fmt::Display for SensorError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorError::I2c(error) => write!(f, "I2C error: {:?}", error),
+            SensorError::InvalidChipId(id) => write!(f, "Unknown chip ID {:#04x}", id),
+            SensorError::InvalidAddress(address) => write!(f, "Invalid I2C address {:#04x}", address),
+            SensorError::CalibrationFailed => write!(f, "Calibration suspect"),
+            SensorError::CalibrationBlank => write!(f, "Calibration blank"),
+            SensorError::Timeout => write!(f, "Timed out waiting for the sensor"),
+            SensorError::NotConfigured => write!(f, "Sensor is not configured"),
+            SensorError::Asleep => write!(f, "Sensor is asleep"),
+            SensorError::Stuck => write!(f, "Sensor is stuck"),
+            SensorError::NoHumidity => write!(f, "Sensor has no humidity channel"),
+            SensorError::Skipped(channel) => write!(f, "{:?} channel skipped", channel),
+            SensorError::OutOfRange(channel) => write!(f, "{:?} reading out of range", channel),
+            SensorError::ConfigMismatch { register, written, read } => write!(
+                f,
+                "Config mismatch on register {:#04x}: wrote {:#04x}, read {:#04x}",
+                register, written, read
+            ),
+            SensorError::BurstStraddled => write!(f, "Burst read kept straddling a conversion"),
+            SensorError::Compensation(error) => write!(f, "Compensation failed: {:?}", error),
+            SensorError::AltitudeOutOfRange => write!(f, "Altitude out of range"),
+            SensorError::NoSamples => write!(f, "No samples to average"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for SensorError<E> {}
+
+
+/// Bus as the driver holds it, putting the sensor to sleep on drop with `sleep-on-drop`.
+#[cfg(not(feature = "sleep-on-drop"))]
+type Device<B> = B;
+#[cfg(feature = "sleep-on-drop")]
+type Device<B> = bus::SleepOnDrop<B>;
+
+
+/// Atmospheric sensor
+///
+/// The driver holds no shared or reference-counted state, so it is `Send` and `Sync` whenever
+/// the bus is, and can be moved to another thread or kept behind a mutex.
+pub struct AtmosphericSensor<B: Bus> {
+    dev: Device<B>,
+    /// `None` until the first read on a sensor built with `new_lazy`.
+    calibration: Option<Calibration>,
+    variant: Variant,
+    config: Config,
+    configured: bool,
+    t_fine: i32,
+    /// Last compensated sample, reused by the derived quantities.
+    cache: Option<Measurements>,
+    /// Samples read since the last change that restarted the IIR filter.
+    filter_samples: u32,
+    /// Sea level pressure in pascal that `get_altitude_meters` is relative to.
+    sea_level_pa: f64,
+    /// Raw samples of `measure_checked`, `None` when it doesn't watch for stale data.
+    watchdog: Option<LivenessWindow>,
+}
+
+impl<I2C: I2c> AtmosphericSensor<AtmosphericSensorI2c<I2C>> {
+    /// Create new AtmosphericSensor device wrapper for I2C communication, for a BME280.
+    ///
+    /// The sensor stays in sleep mode, and reads fail with `SensorError::NotConfigured`, until
+    /// `start`, `apply_config` or `snapshot_config`. The chip ID isn't read, so a miswired or
+    /// different chip goes unnoticed; `try_new` checks it and also accepts a BMP280. Fails
+    /// when the bus errors while the calibration is read.
+    pub fn new(dev: I2C, address: Address) -> Result<Self, SensorError<I2C::Error>> {
+        AtmosphericSensor::with_variant(dev, address, Variant::Bme280)
+    }
+
+    /// Create new AtmosphericSensor, picking the variant from the chip ID.
+    ///
+    /// Fails on an unknown chip ID; `with_variant` skips the detection for parts that report
+    /// an ID the driver doesn't know but share the register map.
+    pub fn try_new(dev: I2C, address: Address) -> Result<Self, SensorError<I2C::Error>> {
+        AtmosphericSensor::detect(AtmosphericSensorI2c::new(dev, checked_address(address)?))
+    }
+
+    /// Create new AtmosphericSensor for a BME280 without touching the bus, for a fast boot.
+    ///
+    /// The calibration is read with the first measurement or configuration that needs it, so a
+    /// dead bus only shows up then; `new` fails fast instead.
+    pub fn new_lazy(dev: I2C, address: Address) -> Result<Self, SensorError<I2C::Error>> {
+        Ok(AtmosphericSensor::wrap(AtmosphericSensorI2c::new(dev, checked_address(address)?), None, Variant::Bme280))
+    }
+
+    /// Create new AtmosphericSensor for a known variant, without reading the chip ID.
+    pub fn with_variant(dev: I2C, address: Address, variant: Variant) -> Result<Self, SensorError<I2C::Error>> {
+        AtmosphericSensor::from_bus(AtmosphericSensorI2c::new(dev, checked_address(address)?), variant)
+    }
+
+    /// Create new AtmosphericSensor from a calibration read earlier, e.g. with `calibration`,
+    /// without touching the bus.
+    ///
+    /// The variant follows from the calibration: BME280 with humidity coefficients, BMP280
+    /// without.
+    pub fn with_calibration(dev: I2C, address: Address, calibration: Calibration) -> Result<Self, SensorError<I2C::Error>> {
+        Ok(AtmosphericSensor::from_bus_with_calibration(AtmosphericSensorI2c::new(dev, checked_address(address)?), calibration))
+    }
+
+    /// Create new AtmosphericSensor, picking the variant from the chip ID like `try_new`, and
+    /// write `config` to it.
+    ///
+    /// The sensor is left in the mode of `config`, sleep included, with none of the defaults of
+    /// `start`.
+    pub fn with_config(dev: I2C, address: Address, config: Config) -> Result<Self, SensorError<I2C::Error>> {
+        let mut sensor = AtmosphericSensor::try_new(dev, address)?;
+        sensor.apply_config(&config)?;
+        Ok(sensor)
+    }
+
+    /// Create new AtmosphericSensor and start it.
+    pub fn build(dev: I2C, address: Address) -> Result<Self, SensorError<I2C::Error>> {
+        let mut sensor = AtmosphericSensor::new(dev, address)?;
+        sensor.start()?;
+        Ok(sensor)
+    }
+
+    /// Give the I2C bus back, e.g. to share it with other devices.
+    ///
+    /// With `sleep-on-drop` the sensor is put to sleep first, ignoring bus errors.
+    pub fn release(self) -> I2C {
+        self.into_bus().release()
+    }
+
+    /// I2C bus the sensor sits on.
+    ///
+    /// Talking to the sensor directly through it can leave the driver out of sync with the
+    /// device; `snapshot_config` reads the configuration back.
+    pub fn bus_mut(&mut self) -> &mut I2C {
+        self.dev.bus_mut()
+    }
+}
+
+impl<SPI: SpiDevice> AtmosphericSensor<AtmosphericSensorSpi<SPI>> {
+    /// Create new AtmosphericSensor device wrapper for SPI communication, for a BME280.
+    ///
+    /// Like `new`, the sensor stays in sleep mode until it is configured.
+    pub fn new_spi(spi: SPI) -> Result<Self, SensorError<SPI::Error>> {
+        AtmosphericSensor::from_bus(AtmosphericSensorSpi::new(spi), Variant::Bme280)
+    }
+
+    /// Create new AtmosphericSensor for SPI communication, picking the variant from the chip
+    /// ID like `try_new`.
+    pub fn try_new_spi(spi: SPI) -> Result<Self, SensorError<SPI::Error>> {
+        AtmosphericSensor::detect(AtmosphericSensorSpi::new(spi))
+    }
+
+    /// Give the SPI device back, like `release` for I2C.
+    pub fn release_spi(self) -> SPI {
+        self.into_bus().release()
+    }
+
+    /// SPI device the sensor sits on, like `bus_mut` for I2C.
+    pub fn spi_mut(&mut self) -> &mut SPI {
+        self.dev.bus_mut()
+    }
+}
+
+impl<B: Bus> AtmosphericSensor<B> {
+    /// Read the calibration of `variant` and wrap a bus.
+    pub fn from_bus(mut bus: B, variant: Variant) -> Result<Self, SensorError<B::Error>> {
+        let calibration = Calibration::build(&mut bus, variant)?;
+        Ok(AtmosphericSensor::wrap(bus, Some(calibration), variant))
+    }
+
+    /// Wrap a bus with a calibration read earlier, like `with_calibration`.
+    pub fn from_bus_with_calibration(bus: B, calibration: Calibration) -> Self {
+        let variant = match calibration.humidity {
+            Some(_) => Variant::Bme280,
+            None => Variant::Bmp280,
+        };
+        AtmosphericSensor::wrap(bus, Some(calibration), variant)
+    }
+
+    fn wrap(dev: B, calibration: Option<Calibration>, variant: Variant) -> Self {
+        AtmosphericSensor {
+            dev: Device::from(dev),
+            calibration,
+            variant,
+            config: Config::default(),
+            configured: false,
+            t_fine: 0,
+            cache: None,
+            filter_samples: 0,
+            sea_level_pa: STANDARD_SEA_LEVEL_PASCAL,
+            watchdog: None,
+        }
+    }
+
+    /// Wrap a bus, picking the variant from the chip ID.
+    pub fn detect(mut bus: B) -> Result<Self, SensorError<B::Error>> {
+        let id = bus.get_id()?;
+        let variant = Variant::from_chip_id(id).ok_or(SensorError::InvalidChipId(id))?;
+        AtmosphericSensor::from_bus(bus, variant)
+    }
+
+    /// Start all parameters from for the sensor
+    pub fn start(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(&self.config.started())
+    }
+
+    /// Write a configuration to the sensor, e.g. one put together with the `Config` builder
+    /// methods. Same as `apply_config`.
+    pub fn apply(&mut self, config: Config) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(&config)
+    }
+
+    /// Write a configuration to the sensor.
+    ///
+    /// Humidity oversampling is ignored, and tracked as skipped, on variants without humidity.
+    ///
+    /// The three control registers are written whole, without reading them first: ctrl_hum,
+    /// config, then ctrl_meas, which makes the humidity setting take effect and sets the mode
+    /// last. A sensor the driver left in normal mode is put to sleep first, as config writes
+    /// may be ignored then. spi3w_en is cleared, the driver only speaks 4-wire SPI.
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), SensorError<B::Error>> {
+        let config = match self.variant.has_humidity() {
+            true => *config,
+            false => Config { humidity_oversampling: Oversampling::Skipped, ..*config },
+        };
+        let (ctrl_hum, ctrl_meas, config_reg) = config.to_registers();
+
+        if self.configured && self.config.mode == Mode::Normal {
+            i2c::write_to_register(&mut self.dev, Register::CtrlMeas, ctrl_meas & 0xFC).map_err(SensorError::I2c)?;
+        }
+        if self.variant.has_humidity() {
+            i2c::write_to_register(&mut self.dev, Register::CtrlHumidity, ctrl_hum).map_err(SensorError::I2c)?;
+        }
+        i2c::write_to_register(&mut self.dev, Register::Config, config_reg).map_err(SensorError::I2c)?;
+        i2c::write_to_register(&mut self.dev, Register::CtrlMeas, ctrl_meas).map_err(SensorError::I2c)?;
+
+        self.track_filter(&config);
+        self.config = config;
+        self.configured = true;
+        self.cache = None;
+        Ok(())
+    }
+
+    /// Write a configuration, then read every control register back and compare.
+    ///
+    /// Fails with the first register that doesn't hold what was written, which on a noisy bus
+    /// means a corrupted write. The mode bits of a forced configuration aren't compared, since
+    /// the sensor may already be back to sleep.
+    pub fn apply_config_verified(&mut self, config: &Config) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(config)?;
+        let (ctrl_hum, ctrl_meas, config_reg) = self.dev.read_control_registers()?;
+        let (expected_hum, expected_meas, expected_config) = self.config.to_registers();
+
+        let mode_mask = match config.mode {
+            Mode::Forced => 0xFC,
+            _ => 0xFF,
+        };
+        let checks = [
+            (registers::CTRL_HUMIDITY_REG, expected_hum, ctrl_hum & 0x07, self.variant.has_humidity()),
+            (registers::CTRL_MEAS_REG, expected_meas & mode_mask, ctrl_meas & mode_mask, true),
+            // spi3w_en and the reserved bit are not part of the configuration
+            (registers::CONFIG_REG, expected_config, config_reg & 0xFC, true),
+        ];
+        for (register, expected, actual, checked) in checks {
+            if checked && expected != actual {
+                return Err(SensorError::ConfigMismatch { register, written: expected, read: actual });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a configuration with as few register writes as possible, returning how many were
+    /// needed.
+    ///
+    /// The control registers are read back in one transaction and only those that differ are
+    /// written. The sensor is put to sleep first when config changes, as writes to it may be
+    /// ignored in normal mode, and ctrl_meas is written after ctrl_hum, which only takes
+    /// effect then.
+    pub fn apply_config_minimal(&mut self, config: &Config) -> Result<usize, SensorError<B::Error>> {
+        let (ctrl_hum, ctrl_meas, config_reg) = self.dev.read_control_registers()?;
+        let (target_hum, target_meas, target_config) = config.to_registers();
+        // Keep the reserved bits and spi3w_en as they are
+        let target_hum = (ctrl_hum & 0xF8) | target_hum;
+        let target_config = (config_reg & 0x03) | target_config;
+
+        // At most ctrl_meas, config, ctrl_hum and ctrl_meas again
+        let mut writes = [(Register::CtrlMeas, 0u8); 4];
+        let mut count = 0;
+        let mut push = |register, value| {
+            writes[count] = (register, value);
+            count += 1;
+        };
+        let mut rewrite_meas = ctrl_meas != target_meas;
+        if config_reg != target_config {
+            if ctrl_meas & 0x03 != 0 {
+                push(Register::CtrlMeas, ctrl_meas & 0xFC);
+                rewrite_meas = true;
+            }
+            push(Register::Config, target_config);
+        }
+        if self.variant.has_humidity() && ctrl_hum != target_hum {
+            push(Register::CtrlHumidity, target_hum);
+            rewrite_meas = true;
+        }
+        if rewrite_meas {
+            push(Register::CtrlMeas, target_meas);
+        }
+
+        for (register, value) in writes[..count].iter() {
+            i2c::write_to_register(&mut self.dev, *register, *value).map_err(SensorError::I2c)?;
+        }
+        self.track_filter(config);
+        self.config = match self.variant.has_humidity() {
+            true => *config,
+            false => Config { humidity_oversampling: Oversampling::Skipped, ..*config },
+        };
+        self.configured = true;
+        self.cache = None;
+        Ok(count)
+    }
+
+    /// Read the configuration back from the control registers.
+    ///
+    /// The tracked configuration is updated to match what the sensor reports.
+    pub fn snapshot_config(&mut self) -> Result<Config, SensorError<B::Error>> {
+        let (ctrl_hum, ctrl_meas, config) = self.dev.read_control_registers()?;
+        // The offset and range policy only live in the driver
+        self.config = Config {
+            temperature_offset_c: self.config.temperature_offset_c,
+            out_of_range: self.config.out_of_range,
+            ..Config::from_registers(ctrl_hum, ctrl_meas, config)
+        };
+        // Whoever wrote these registers, the driver now knows what they hold
+        self.configured = true;
+        Ok(self.config)
+    }
+
+    /// Write back a configuration taken with `snapshot_config`, e.g. after a power-gated sleep.
+    pub fn restore_config(&mut self, config: &Config) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(config)
+    }
+
+    /// Stop sampling humidity, e.g. to save power, leaving the other channels as they are.
+    ///
+    /// Only the humidity oversampling bits change. Fails with `SensorError::NoHumidity` on a
+    /// BMP280.
+    pub fn disable_humidity(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.enable_humidity(Oversampling::Skipped)
+    }
+
+    /// Sample humidity again with `oversampling`, leaving the other channels as they are.
+    ///
+    /// ctrl_meas is written back unchanged so the new humidity setting takes effect.
+    pub fn enable_humidity(&mut self, oversampling: Oversampling) -> Result<(), SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        self.dev.set_humidity_oversample(oversampling)?;
+        self.config.humidity_oversampling = oversampling;
+        self.cache = None;
+        Ok(())
+    }
+
+    /// Stop sampling pressure, leaving the other channels as they are.
+    pub fn disable_pressure(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.enable_pressure(Oversampling::Skipped)
+    }
+
+    /// Sample pressure again with `oversampling`, leaving the other channels as they are.
+    pub fn enable_pressure(&mut self, oversampling: Oversampling) -> Result<(), SensorError<B::Error>> {
+        self.dev.set_pressure_oversample(oversampling)?;
+        self.config.pressure_oversampling = oversampling;
+        self.cache = None;
+        Ok(())
+    }
+
+    /// Configure the sensor with the low power preset.
+    pub fn configure_low_power(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(&Config::low_power())
+    }
+
+    /// Configure the sensor with the high accuracy preset.
+    pub fn configure_high_accuracy(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(&Config::high_accuracy())
+    }
+
+    /// Write one of the datasheet's recommended configurations, keeping the temperature offset
+    /// and range policy already set.
+    pub fn apply_profile(&mut self, profile: Profile) -> Result<(), SensorError<B::Error>> {
+        self.apply_config(&Config {
+            temperature_offset_c: self.config.temperature_offset_c,
+            out_of_range: self.config.out_of_range,
+            ..profile.config()
+        })
+    }
+
+    /// Stop the sensor.
+    pub fn stop(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.dev.set_mode(Mode::Sleep)?;
+        self.config.mode = Mode::Sleep;
+        self.filter_samples = 0;
+        Ok(())
+    }
+
+    /// Reset device.
+    ///
+    /// The tracked configuration and t_fine are dropped. The calibration is kept, use
+    /// `reset_and_wait` to read it again once the sensor finished copying it.
+    pub fn reset(&mut self) -> Result<(), SensorError<B::Error>> {
+        self.dev.reset()?;
+        self.config = Config {
+            temperature_offset_c: self.config.temperature_offset_c,
+            out_of_range: self.config.out_of_range,
+            ..Config::default()
+        };
+        self.configured = false;
+        self.t_fine = 0;
+        self.cache = None;
+        self.filter_samples = 0;
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.clear();
+        }
+        Ok(())
+    }
+
+    /// Reset device and wait until it copied its NVM data to the image registers.
+    ///
+    /// Reads during the copy return invalid data, so the status is polled until `im_update`
+    /// clears, failing with `SensorError::Timeout` if it doesn't. The calibration is then read
+    /// again from the freshly copied registers.
+    pub fn reset_and_wait<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), SensorError<B::Error>> {
+        self.reset()?;
+        for _ in 0..RESET_POLL_ATTEMPTS {
+            // Wait first, the sensor doesn't answer right after the reset command
+            delay.delay_us(RESET_POLL_INTERVAL_US);
+            if !self.is_updating()? {
+                self.calibration = Some(Calibration::build(&mut self.dev, self.variant)?);
+                return Ok(());
+            }
+        }
+        Err(SensorError::Timeout)
+    }
+
+    /// Has the IIR filter settled since the last change of filter, standby time or mode.
+    ///
+    /// Until then the filtered output still leans towards the samples before the change.
+    pub fn filter_settled(&self) -> bool {
+        self.filter_discard_count() == 0
+    }
+
+    /// Samples still to discard before the IIR filter settles, from the datasheet step response.
+    ///
+    /// Every burst read counts as one sample, so in normal mode read at most once per output
+    /// data period, e.g. with `read_many`, for the count to hold.
+    pub fn filter_discard_count(&self) -> u32 {
+        self.config.filter.settling_samples().saturating_sub(self.filter_samples)
+    }
+
+    /// Drop the cached sample so the next derived quantity reads a fresh one.
+    pub fn invalidate_cache(&mut self) {
+        self.cache = None;
+    }
+
+    /// Take one forced measurement and check it against the operating ranges, to catch a
+    /// corrupt or mis-parsed calibration at startup.
+    ///
+    /// Meant to run right after construction. An unconfigured sensor is left with the weather
+    /// monitoring preset, otherwise the previous configuration is written back. Fails with
+    /// `SensorError::CalibrationFailed` when temperature or pressure fall outside `ranges`.
+    pub fn verify_calibration<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), SensorError<B::Error>> {
+        let previous = self.configured.then_some(self.config);
+        // The preset passes readings through, so the range check sees them as they are
+        self.apply_config(&Config {
+            mode: Mode::Sleep,
+            temperature_offset_c: self.config.temperature_offset_c,
+            ..Config::weather_monitoring()
+        })?;
+        let sample = self.measure_forced(delay)?;
+        if let Some(previous) = previous {
+            self.apply_config(&previous)?;
+        }
+
+        let plausible = ranges::TEMPERATURE_CELSIUS.contains(&sample.temperature_celsius)
+            && ranges::PRESSURE_PASCAL.contains(&sample.pressure_pascal);
+        match plausible {
+            true => Ok(()),
+            false => Err(SensorError::CalibrationFailed),
+        }
+    }
+
+    /// Go/no-go check for production firmware at startup.
+    ///
+    /// Fails with `SensorError::InvalidChipId` when the chip ID isn't the one of the variant,
+    /// `SensorError::CalibrationBlank` on a blank calibration, and otherwise runs
+    /// `verify_calibration`, which takes one forced measurement.
+    pub fn self_test<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), SensorError<B::Error>> {
+        let id = self.dev.get_id()?;
+        if Variant::from_chip_id(id) != Some(self.variant) {
+            return Err(SensorError::InvalidChipId(id));
+        }
+        if self.calibration()?.is_blank() {
+            return Err(SensorError::CalibrationBlank);
+        }
+        self.verify_calibration(delay)
+    }
+
+    /// Sensor variant this driver talks to.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Calibration read from the sensor, to be stored and handed to `with_calibration` later.
+    ///
+    /// Reads it first on a sensor built with `new_lazy` that hasn't needed it yet.
+    pub fn calibration(&mut self) -> Result<Calibration, SensorError<B::Error>> {
+        if let Some(calibration) = self.calibration {
+            return Ok(calibration);
+        }
+        let calibration = Calibration::build(&mut self.dev, self.variant)?;
+        self.calibration = Some(calibration);
+        Ok(calibration)
+    }
+
+    /// Read the chip ID, control, status and data registers as they are, for diagnostics.
+    pub fn dump_registers(&mut self) -> Result<RegisterDump, SensorError<B::Error>> {
+        Ok(self.dev.dump_registers()?)
+    }
+
+    /// Read the status register once and decode both of its flags.
+    pub fn status(&mut self) -> Result<Status, SensorError<B::Error>> {
+        Ok(self.dev.get_status()?)
+    }
+
+    /// Is the device measuring.
+    pub fn is_measuring(&mut self) -> Result<bool, SensorError<B::Error>> {
+        Ok(self.status()?.measuring)
+    }
+
+    /// Is the device copying NVM data to image registers.
+    pub fn is_updating(&mut self) -> Result<bool, SensorError<B::Error>> {
+        Ok(self.status()?.im_update)
+    }
+
+    /// Mode the sensor reports, which goes back to sleep on its own after a forced measurement.
+    pub fn get_mode(&mut self) -> Result<Mode, SensorError<B::Error>> {
+        Ok(self.dev.get_mode()?)
+    }
+
+    /// Temperature oversampling the sensor reports.
+    pub fn get_temperature_oversample(&mut self) -> Result<Oversampling, SensorError<B::Error>> {
+        Ok(self.dev.get_temperature_oversample()?)
+    }
+
+    /// Pressure oversampling the sensor reports.
+    pub fn get_pressure_oversample(&mut self) -> Result<Oversampling, SensorError<B::Error>> {
+        Ok(self.dev.get_pressure_oversample()?)
+    }
+
+    /// Humidity oversampling the sensor reports.
+    pub fn get_humidity_oversample(&mut self) -> Result<Oversampling, SensorError<B::Error>> {
+        Ok(self.dev.get_humidity_oversample()?)
+    }
+
+    /// Filter coefficient the sensor reports.
+    pub fn get_filter(&mut self) -> Result<Filter, SensorError<B::Error>> {
+        Ok(self.dev.get_filter()?)
+    }
+
+    /// Standby time the sensor reports.
+    pub fn get_standby_time(&mut self) -> Result<StandbyTime, SensorError<B::Error>> {
+        Ok(self.dev.get_standby_time()?)
+    }
+
+    /// Logical state of the sensor, from the tracked configuration and the status and
+    /// ctrl_meas registers, read in one transaction.
+    ///
+    /// An unconfigured sensor is reported without touching the bus. A forced configuration
+    /// the sensor already dropped back to sleep from is ready, not a fault.
+    pub fn state(&mut self) -> Result<SensorState, SensorError<B::Error>> {
+        if !self.configured {
+            return Ok(SensorState::Uninitialized);
+        }
+        // status and ctrl_meas are consecutive
+        let mut buffer = [0u8; 2];
+        i2c::read_from_register(&mut self.dev, Register::Status, &mut buffer).map_err(SensorError::I2c)?;
+        let [status, ctrl_meas] = buffer;
+        let status = Status::from(status);
+
+        let mode = Mode::from_field(ctrl_meas & 0x03);
+        let consistent = match self.config.mode {
+            Mode::Forced => mode != Mode::Normal,
+            tracked => mode == tracked,
+        };
+        let state = if status.im_update {
+            SensorState::Uninitialized
+        } else if !consistent {
+            SensorState::Fault
+        } else if status.measuring {
+            SensorState::Measuring
+        } else if self.config.mode == Mode::Sleep {
+            SensorState::Sleeping
+        } else {
+            SensorState::Ready
+        };
+        Ok(state)
+    }
+
+    /// Get temperature in celsius from sensor.
+    pub fn get_temperature_celsius(&mut self) -> Result<f64, SensorError<B::Error>> {
+        let t_fine = self.read_t_fine()?;
+        let temperature_celsius = self.calibration()?.temperature_celsius(t_fine);
+        self.config.out_of_range.apply(Channel::Temperature, temperature_celsius)
+    }
+
+    /// Get temperature in celsius from sensor without rounding to centidegrees.
+    ///
+    /// The extra digits are resolution, not accuracy: they come straight from `t_fine` and
+    /// help control loops avoid quantization steps, but the sensor is no more accurate.
+    pub fn get_temperature_celsius_precise(&mut self) -> Result<f64, SensorError<B::Error>> {
+        let t_fine = self.read_t_fine()?;
+        self.config.out_of_range.apply(Channel::Temperature, f64::from(t_fine) / 5120.0)
+    }
+
+    /// Get temperature in fahrenheit from sensor, converted from `get_temperature_celsius`.
+    pub fn get_temperature_fahrenheit(&mut self) -> Result<f64, SensorError<B::Error>> {
+        Ok(self.get_temperature_celsius()? * 1.8 + 32.0)
+    }
+
+    /// Get temperature in kelvin from sensor, converted from `get_temperature_celsius`.
+    pub fn get_temperature_kelvin(&mut self) -> Result<f64, SensorError<B::Error>> {
+        Ok(self.get_temperature_celsius()? + 273.15)
+    }
+
+    /// Get temperature in whole degrees celsius, rounded half up from `get_temperature_celsius`.
+    ///
+    /// The sensor resolves 0.01 °C, so only the rounding to whole degrees loses resolution.
+    pub fn get_temperature_celsius_rounded(&mut self) -> Result<i32, SensorError<B::Error>> {
+        Ok(round_half_up(self.get_temperature_celsius()?))
+    }
+
+    /// Get temperature in celsius from one sample taken with the IIR filter off.
+    ///
+    /// The sensor is put to sleep so the filter change is accepted, a forced measurement is
+    /// taken without the filter and the previous configuration is written back. Turning the
+    /// filter back on restarts its settling, so the next filtered samples are not steady state.
+    pub fn get_temperature_celsius_unfiltered<D: DelayNs>(&mut self, delay: &mut D) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_configured()?;
+        let previous = self.config;
+        let temperature_oversampling = match previous.temperature_oversampling {
+            Oversampling::Skipped => Oversampling::Ox1,
+            rate => rate,
+        };
+        let unfiltered = Config { mode: Mode::Forced, filter: Filter::Off, temperature_oversampling, ..previous };
+
+        self.apply_config(&unfiltered)?;
+        delay.delay_us(self.measurement_time_us());
+        let temperature = self.get_temperature_celsius();
+
+        self.apply_config(&previous)?;
+        temperature
+    }
+
+    /// Get pressure in pascal from sensor.
+    pub fn get_pressure_pascal(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        let adc_p = self.dev.get_pressure_raw()?;
+        ensure_sampled(Channel::Pressure, adc_p)?;
+        let pressure = self.compensate_pressure(adc_p)?;
+        self.config.out_of_range.apply(Channel::Pressure, pressure)
+    }
+
+    /// Get pressure in hectopascal from sensor, converted from `get_pressure_pascal`.
+    pub fn get_pressure_hpa(&mut self) -> Result<f64, SensorError<B::Error>> {
+        Ok(self.get_pressure_pascal()? / 100.0)
+    }
+
+    /// Get pressure in kilopascal from sensor, converted from `get_pressure_pascal`.
+    pub fn get_pressure_kpa(&mut self) -> Result<f64, SensorError<B::Error>> {
+        Ok(self.get_pressure_pascal()? / 1000.0)
+    }
+
+    /// Get pressure in millimetres of mercury from sensor, converted from `get_pressure_pascal`.
+    pub fn get_pressure_mmhg(&mut self) -> Result<f64, SensorError<B::Error>> {
+        Ok(self.get_pressure_pascal()? * MMHG_PER_PASCAL)
+    }
+
+    /// Get pressure in inches of mercury from sensor, converted from `get_pressure_pascal`.
+    pub fn get_pressure_inhg(&mut self) -> Result<f64, SensorError<B::Error>> {
+        Ok(self.get_pressure_pascal()? * INHG_PER_PASCAL)
+    }
+
+    /// Get pressure in whole pascal, rounded half up from `get_pressure_pascal`.
+    ///
+    /// The compensated pressure resolves 1/256 Pa, well below the sensor noise.
+    pub fn get_pressure_pascal_rounded(&mut self) -> Result<i32, SensorError<B::Error>> {
+        Ok(round_half_up(self.get_pressure_pascal()?))
+    }
+
+    /// Get pressure in whole hectopascal, rounded half up from `get_pressure_pascal`.
+    ///
+    /// Rounding the pascal value once avoids the downward drift of truncating `get_pressure_hpa`.
+    pub fn get_pressure_hpa_rounded(&mut self) -> Result<i32, SensorError<B::Error>> {
+        Ok(round_half_up(self.get_pressure_pascal()? / 100.0))
+    }
+
+    /// Get relative humidity in percent from sensor.
+    ///
+    /// Fails with `SensorError::NoHumidity` on a BMP280, whose humidity calibration isn't read.
+    pub fn get_humidity_relative(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        self.ensure_humidity()?;
+        let adc_h = self.dev.get_humidity_raw()?;
+        ensure_sampled(Channel::Humidity, adc_h)?;
+        let humidity = self.compensate_humidity(adc_h)?;
+        self.config.out_of_range.apply(Channel::Humidity, humidity)
+    }
+
+    /// Get relative humidity in whole percent, rounded half up from `get_humidity_relative`.
+    ///
+    /// The compensated humidity resolves 1/1024 %.
+    pub fn get_humidity_relative_rounded(&mut self) -> Result<i32, SensorError<B::Error>> {
+        Ok(round_half_up(self.get_humidity_relative()?))
+    }
+
+    /// Get relative humidity in percent, reading the temperature it is compensated with in the
+    /// same transaction.
+    ///
+    /// Temperature and humidity registers are contiguous, so one burst from temperature MSB
+    /// replaces a temperature read followed by a humidity read.
+    pub fn read_humidity_only(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        self.ensure_humidity()?;
+        let (adc_t, adc_h) = self.dev.read_temperature_humidity_raw()?;
+        ensure_sampled(Channel::Humidity, adc_h)?;
+        self.compensate_t_fine(adc_t)?;
+        let humidity = self.compensate_humidity(adc_h)?;
+        self.config.out_of_range.apply(Channel::Humidity, humidity)
+    }
+
+    /// Maximum time in microseconds for one measurement with the configured oversampling.
+    pub fn measurement_time_us(&self) -> u32 {
+        let temperature = self.config.temperature_oversampling.factor();
+        let pressure = self.config.pressure_oversampling.factor();
+        let humidity = self.config.humidity_oversampling.factor();
+
+        // Datasheet appendix B: t_measure,max in microseconds
+        let mut time = 1250 + 2300 * temperature;
+        if pressure > 0 {
+            time += 2300 * pressure + 575;
+        }
+        if humidity > 0 {
+            time += 2300 * humidity + 575;
+        }
+        time
+    }
+
+    /// Time in microseconds between two samples in normal mode, measurement plus standby.
+    fn output_data_period_us(&self) -> u32 {
+        self.measurement_time_us() + self.config.standby_time.micros()
+    }
+
+    /// `measurement_time_us` rounded up to whole milliseconds, for delays with millisecond
+    /// resolution.
+    pub fn max_measurement_time_ms(&self) -> u32 {
+        self.measurement_time_us().div_ceil(1000)
+    }
+
+    /// Time in microseconds from leaving sleep mode until the first sample with the configured
+    /// oversampling is in the data registers.
+    ///
+    /// Covers the start-up time plus one measurement; reading earlier returns the previous
+    /// sample or the reset values.
+    pub fn startup_time_us(&self) -> u32 {
+        STARTUP_TIME_US + self.measurement_time_us()
+    }
+
+    /// Read temperature, pressure and humidity from the same sample.
+    ///
+    /// All data registers are read in a single burst, which the sensor shadows so the bytes
+    /// belong to one conversion. Temperature is compensated first, so pressure and humidity
+    /// use the t_fine of that same conversion rather than one left by an earlier read.
+    pub fn measure(&mut self) -> Result<Measurements, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        let raw = RawMeasurement::from(self.read_burst_checked()?);
+        self.compensate(&raw)
+    }
+
+    /// Like `measure`, but compensated with the double precision formulas of the datasheet.
+    ///
+    /// They avoid the truncation steps of the integer formulas, at the cost of floating point
+    /// arithmetic. Results differ from `measure` by less than 0.01 °C, as that rounds to
+    /// centidegrees, 1 Pa and 0.01 %RH.
+    pub fn measure_f64(&mut self) -> Result<Measurements, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        let raw = RawMeasurement::from(self.read_burst_checked()?);
+        let sample = compensate_sample_f64(&self.calibration()?, &raw, &self.config)?;
+        self.cache = Some(sample);
+        Ok(sample)
+    }
+
+    /// Fill `out` with consecutive measurements and return how many were taken.
+    ///
+    /// In normal mode samples are paced to the output data rate, measurement time plus the
+    /// configured standby time, and in forced mode each slot triggers its own measurement. An
+    /// error on the first sample is returned as is; after that, reading stops at the first error
+    /// and the count of samples already stored is returned.
+    pub fn read_many<D: DelayNs>(&mut self, out: &mut [Measurements], delay: &mut D) -> Result<usize, SensorError<B::Error>> {
+        let period_us = self.output_data_period_us();
+
+        for (index, slot) in out.iter_mut().enumerate() {
+            let sample = match self.config.mode {
+                Mode::Forced => self.measure_forced(delay),
+                _ => {
+                    if index > 0 {
+                        delay.delay_us(period_us);
+                    }
+                    self.measure()
+                }
+            };
+            match sample {
+                Ok(sample) => *slot = sample,
+                Err(error) if index == 0 => return Err(error),
+                Err(_) => return Ok(index),
+            }
+        }
+        Ok(out.len())
+    }
+
+    /// Endless iterator of measurements paced like `read_many`.
+    ///
+    /// In normal mode each item waits one output data period before reading, so it is never
+    /// read faster than the sensor converts; in forced mode each item triggers a measurement.
+    /// Errors are yielded and iteration can go on after them.
+    pub fn measurements<'a, D: DelayNs>(&'a mut self, delay: &'a mut D) -> MeasurementStream<'a, B, D> {
+        MeasurementStream::new(self, delay)
+    }
+
+    /// Read temperature, pressure and humidity from the same sample, failing with
+    /// `SensorError::Stuck` when `window` finds the raw data stopped changing.
+    pub fn measure_watched(&mut self, window: &mut LivenessWindow) -> Result<Measurements, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        let raw = RawMeasurement::from(self.read_burst_checked()?);
+        if window.update(&raw) {
+            return Err(SensorError::Stuck);
+        }
+        self.compensate(&raw)
+    }
+
+    /// Read the data registers as they are, from pressure MSB to humidity LSB, in one burst.
+    ///
+    /// Nothing is compensated, so this is the cheapest way to log samples and compensate them
+    /// later with `RawMeasurement` and `compensate`. The status register isn't checked either;
+    /// use `measure` to get the retry on reads that straddle a conversion.
+    pub fn read_raw_bytes(&mut self) -> Result<[u8; DATA_FRAME_LENGTH], SensorError<B::Error>> {
+        self.ensure_awake()?;
+        Ok(self.dev.read_burst()?)
+    }
+
+    /// Take one measurement in forced mode and read it.
+    ///
+    /// Oversampling and filter must be configured beforehand, e.g. with `apply_config`. The
+    /// sensor is triggered, left alone for `measurement_time_us` with every enabled channel
+    /// accounted for, and read once without polling the status register. It goes back to
+    /// sleep on its own afterwards.
+    pub fn measure_forced<D: DelayNs>(&mut self, delay: &mut D) -> Result<Measurements, SensorError<B::Error>> {
+        let raw = self.read_forced(delay)?;
+        self.compensate(&raw)
+    }
+
+    /// Like `measure_forced`, failing with `SensorError::Stuck` once a channel repeats its raw
+    /// value for more reads in a row than the limit set with `set_stale_data_limit`.
+    ///
+    /// Catches data registers frozen after a brown-out, which compensation can't tell from a
+    /// steady reading. Without a limit this is `measure_forced`.
+    pub fn measure_checked<D: DelayNs>(&mut self, delay: &mut D) -> Result<Measurements, SensorError<B::Error>> {
+        let raw = self.read_forced(delay)?;
+        if let Some(watchdog) = &mut self.watchdog {
+            if watchdog.update(&raw) {
+                return Err(SensorError::Stuck);
+            }
+        }
+        self.compensate(&raw)
+    }
+
+    /// Watch `measure_checked` for a channel repeating its raw value more than `limit` reads in
+    /// a row, see `LivenessWindow::per_channel`, or stop watching with `None`.
+    pub fn set_stale_data_limit(&mut self, limit: Option<usize>) {
+        self.watchdog = limit.map(LivenessWindow::per_channel);
+    }
+
+    /// Take one measurement in forced mode, polling the status register until it is done.
+    ///
+    /// Useful when the configured oversampling isn't known, e.g. after `snapshot_config`
+    /// failed. `measure_forced` is cheaper on the bus when it is. For the same reason it isn't
+    /// refused on a sensor the driver hasn't configured.
+    pub fn measure_forced_polled<D: DelayNs>(&mut self, delay: &mut D) -> Result<Measurements, SensorError<B::Error>> {
+        self.dev.set_mode(Mode::Forced)?;
+        self.config.mode = Mode::Forced;
+
+        for _ in 0..FORCED_POLL_ATTEMPTS {
+            // Wait first, the measuring bit may not be set right after the mode write
+            delay.delay_us(FORCED_POLL_INTERVAL_US);
+            if !self.is_measuring()? {
+                let raw = RawMeasurement::from(self.dev.read_burst()?);
+                return self.compensate(&raw);
+            }
+        }
+        Err(SensorError::Timeout)
+    }
+
+    /// Take one measurement in forced mode without a delay provider, reading the status
+    /// register back to back, for boards without a timer.
+    ///
+    /// The data is only read once the measuring flag was seen set and then cleared, so a poll
+    /// that comes before the conversion starts isn't mistaken for its end. Each poll is one bus
+    /// transaction, so `max_polls` must cover `measurement_time_us` at the bus speed in use;
+    /// past it this fails with `SensorError::Timeout`. `measure_forced` and
+    /// `measure_forced_polled` let the CPU sleep instead.
+    pub fn measure_forced_busy(&mut self, max_polls: usize) -> Result<Measurements, SensorError<B::Error>> {
+        self.ensure_configured()?;
+        self.dev.set_mode(Mode::Forced)?;
+        self.config.mode = Mode::Forced;
+
+        let mut started = false;
+        for _ in 0..max_polls {
+            match self.is_measuring()? {
+                true => started = true,
+                false if started => {
+                    let raw = RawMeasurement::from(self.dev.read_burst()?);
+                    return self.compensate(&raw);
+                }
+                false => {}
+            }
+        }
+        Err(SensorError::Timeout)
+    }
+
+    /// Take `samples` forced measurements with `measure_forced` and combine them, for software
+    /// smoothing that also covers humidity, which the IIR filter leaves alone.
+    ///
+    /// Temperature and humidity are averaged, pressure is the median so a single spike is
+    /// rejected. This takes `samples` times `measurement_time_us`. 0 samples are rejected with
+    /// `SensorError::NoSamples` before touching the bus, and any failed measurement fails the
+    /// whole call. The combined result replaces the last measurement in the cache.
+    pub fn measure_averaged<D: DelayNs>(&mut self, samples: u8, delay: &mut D) -> Result<Measurements, SensorError<B::Error>> {
+        if samples == 0 {
+            return Err(SensorError::NoSamples);
+        }
+        let count = usize::from(samples);
+        let mut pressures = [0.0f64; u8::MAX as usize];
+        let mut temperature_sum = 0.0;
+        let mut humidity_sum = 0.0;
+
+        for pressure in pressures[..count].iter_mut() {
+            let sample = self.measure_forced(delay)?;
+            temperature_sum += sample.temperature_celsius;
+            humidity_sum += sample.humidity_percent;
+            *pressure = sample.pressure_pascal;
+        }
+
+        let pressures = &mut pressures[..count];
+        pressures.sort_unstable_by(f64::total_cmp);
+        let pressure_pascal = match count % 2 {
+            0 => (pressures[count / 2 - 1] + pressures[count / 2]) / 2.0,
+            _ => pressures[count / 2],
+        };
+        let averaged = Measurements {
+            temperature_celsius: temperature_sum / count as f64,
+            pressure_pascal,
+            humidity_percent: humidity_sum / count as f64,
+        };
+        self.cache = Some(averaged);
+        Ok(averaged)
+    }
+
+    /// Compensate a raw sample, for instance one captured from the bus earlier, with this
+    /// sensor's calibration.
+    pub fn compensate(&mut self, raw: &RawMeasurement) -> Result<Measurements, SensorError<B::Error>> {
+        let (sample, t_fine) = compensate_sample(&self.calibration()?, raw, &self.config)?;
+        self.t_fine = t_fine;
+        self.cache = Some(sample);
+        Ok(sample)
+    }
+
+    /// Read temperature in tenths of a degree, pressure in hectopascal and humidity in percent,
+    /// for small displays.
+    ///
+    /// Each value is rounded to the nearest unit from the integer compensation output, so the
+    /// path from the bus to the tuple needs no floating point besides the temperature offset
+    /// and the range check. `Config::out_of_range` is applied like in the other getters, a
+    /// clamped value becomes the bound in display units. Every channel must be sampled, so
+    /// this fails on variants without humidity.
+    pub fn read_display_tuple(&mut self) -> Result<(i16, u16, u8), SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        self.ensure_awake()?;
+        let raw = RawMeasurement::from(self.read_burst_checked()?);
+
+        let t_fine = self.compensate_t_fine(raw.temperature)?;
+        ensure_sampled(Channel::Pressure, raw.pressure)?;
+        ensure_sampled(Channel::Humidity, raw.humidity)?;
+        let calibration = self.calibration()?;
+        let pressure = calibration.pressure.compensate_pressure(raw.pressure as i32, t_fine)?;
+        let humidity = match &calibration.humidity {
+            Some(calibration) => calibration.compensate_humidity(raw.humidity as i32, t_fine)?,
+            None => return Err(SensorError::NoHumidity),
+        };
+
+        let policy = self.config.out_of_range;
+        let tenths = display_in_range(policy, Channel::Temperature, f64::from(t_fine) / 5120.0, i32::from(tenths_from_t_fine(t_fine)), 10.0)?;
+        // Q24.8 pascal, 25600 counts per hectopascal
+        let hectopascal = display_in_range(policy, Channel::Pressure, f64::from(pressure) / 256.0, ((pressure + 12800) / 25600) as i32, 0.01)?;
+        // Q22.10 percent, at most 100
+        let percent = display_in_range(policy, Channel::Humidity, f64::from(humidity) / 1024.0, ((humidity + 512) >> 10) as i32, 1.0)?;
+
+        Ok((tenths as i16, hectopascal as u16, percent as u8))
+    }
+
+    /// Read a weather snapshot, with altitude relative to the given sea level pressure in pascal.
+    #[cfg(feature = "std")]
+    pub fn read_weather(&mut self, sea_level_pa: f64) -> Result<Weather, SensorError<B::Error>> {
+        let sample = self.measure()?;
+
+        Ok(Weather {
+            temperature_celsius: sample.temperature_celsius,
+            pressure_pascal: sample.pressure_pascal,
+            humidity_percent: sample.humidity_percent,
+            altitude_meters: derived::altitude_meters(sample.pressure_pascal, sea_level_pa),
+            dew_point_celsius: derived::dew_point_celsius(sample.temperature_celsius, sample.humidity_percent),
+        })
+    }
+
+    /// Sea level pressure in pascal that `get_altitude_meters` is relative to, the standard
+    /// atmosphere until set or calibrated.
+    pub fn sea_level_pressure_pa(&self) -> f64 {
+        self.sea_level_pa
+    }
+
+    /// Set the sea level pressure in pascal that `get_altitude_meters` is relative to, e.g.
+    /// the QNH of the nearest airport.
+    pub fn set_sea_level_pressure_pa(&mut self, sea_level_pa: f64) {
+        self.sea_level_pa = sea_level_pa;
+    }
+
+    /// Altitude in meters from a fresh measurement, relative to the stored sea level pressure.
+    #[cfg(feature = "std")]
+    pub fn get_altitude_meters(&mut self) -> Result<f64, SensorError<B::Error>> {
+        let sample = self.measure()?;
+        Ok(derived::altitude_meters(sample.pressure_pascal, self.sea_level_pa))
+    }
+
+    /// Zero the altimeter at a known altitude in meters, e.g. the launch point.
+    ///
+    /// The pressure is measured, the sea level pressure that puts it at `known_altitude_m` is
+    /// stored for `get_altitude_meters` and returned in pascal. Weather still moves the
+    /// reference, so altitudes drift over hours.
+    #[cfg(feature = "std")]
+    pub fn calibrate_altitude(&mut self, known_altitude_m: f64) -> Result<f64, SensorError<B::Error>> {
+        let sample = self.measure()?;
+        self.sea_level_pa = derived::sea_level_pressure_pascal(sample.pressure_pascal, known_altitude_m)
+            .ok_or(SensorError::AltitudeOutOfRange)?;
+        Ok(self.sea_level_pa)
+    }
+
+    /// Pressure in pascal from a fresh measurement, reduced to sea level (QNH) for a station at
+    /// `station_altitude_m` meters, see `derived::sea_level_pressure_pascal`.
+    #[cfg(feature = "std")]
+    pub fn get_pressure_sea_level_pa(&mut self, station_altitude_m: f64) -> Result<f64, SensorError<B::Error>> {
+        let sample = self.measure()?;
+        derived::sea_level_pressure_pascal(sample.pressure_pascal, station_altitude_m)
+            .ok_or(SensorError::AltitudeOutOfRange)
+    }
+
+    /// Dew point in celsius of the cached sample.
+    ///
+    /// Like the other derived quantities, this reuses the last compensated sample, from
+    /// `measure` or any other reading of all channels, and only measures when there is none.
+    /// Call `invalidate_cache` to force a fresh sample.
+    #[cfg(feature = "std")]
+    pub fn get_dew_point_celsius(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        let sample = self.cached_sample()?;
+        Ok(derived::dew_point_celsius(sample.temperature_celsius, sample.humidity_percent))
+    }
+
+    /// Absolute humidity in g/m³ of the cached sample, see `get_absolute_humidity` for a fresh one.
+    #[cfg(feature = "std")]
+    pub fn get_absolute_humidity_gm3(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        let sample = self.cached_sample()?;
+        Ok(derived::absolute_humidity_gm3(sample.temperature_celsius, sample.humidity_percent))
+    }
+
+    /// Absolute humidity in g/m³ from a fresh `measure`, so temperature and humidity come from
+    /// the same cycle, see `derived::absolute_humidity_bolton_gm3`.
+    #[cfg(feature = "std")]
+    pub fn get_absolute_humidity(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        let sample = self.measure()?;
+        Ok(derived::absolute_humidity_bolton_gm3(sample.temperature_celsius, sample.humidity_percent))
+    }
+
+    /// NOAA heat index in celsius, the apparent temperature of the cached sample, see
+    /// `derived::heat_index_celsius`.
+    #[cfg(feature = "std")]
+    pub fn get_heat_index_celsius(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        let sample = self.cached_sample()?;
+        Ok(derived::heat_index_celsius(sample.temperature_celsius, sample.humidity_percent))
+    }
+
+    /// Density of the air in kg/m³, with temperature, pressure and humidity of the cached sample.
+    #[cfg(feature = "std")]
+    pub fn get_air_density_kgm3(&mut self) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        let sample = self.cached_sample()?;
+        Ok(derived::air_density_kgm3(sample.temperature_celsius, sample.pressure_pascal, sample.humidity_percent))
+    }
+
+    /// Relative humidity in percent the current air would have at another temperature in
+    /// celsius, e.g. next to a colder surface.
+    ///
+    /// The absolute humidity of the cached sample is kept constant. Values above 100 mean
+    /// water would condense at that temperature.
+    #[cfg(feature = "std")]
+    pub fn humidity_at_temperature(&mut self, target_celsius: f64) -> Result<f64, SensorError<B::Error>> {
+        self.ensure_humidity()?;
+        let sample = self.cached_sample()?;
+        let absolute = derived::absolute_humidity_gm3(sample.temperature_celsius, sample.humidity_percent);
+        Ok(derived::relative_humidity_percent(absolute, target_celsius))
+    }
+
+    /// Last compensated sample, measuring one when there is none.
+    #[cfg(feature = "std")]
+    fn cached_sample(&mut self) -> Result<Measurements, SensorError<B::Error>> {
+        match self.cache {
+            Some(sample) => Ok(sample),
+            None => self.measure(),
+        }
+    }
+
+    /// Burst read the data registers, retrying when a conversion finished during the read.
+    ///
+    /// The status register is read before and after the burst. If the measuring bit went from
+    /// set to clear, results were transferred to the data registers while we were reading, so
+    /// the burst is discarded and taken again.
+    fn read_burst_checked(&mut self) -> Result<[u8; DATA_FRAME_LENGTH], SensorError<B::Error>> {
+        for _ in 0..BURST_ATTEMPTS {
+            let measuring_before = self.is_measuring()?;
+            let data = self.dev.read_burst()?;
+            let measuring_after = self.is_measuring()?;
+
+            let straddled = measuring_before && !measuring_after;
+            if !straddled {
+                self.filter_samples = self.filter_samples.saturating_add(1);
+                return Ok(data);
+            }
+        }
+        Err(SensorError::BurstStraddled)
+    }
+
+    /// Trigger a forced measurement, wait `measurement_time_us` and read the data registers.
+    fn read_forced<D: DelayNs>(&mut self, delay: &mut D) -> Result<RawMeasurement, SensorError<B::Error>> {
+        self.ensure_configured()?;
+        self.dev.set_mode(Mode::Forced)?;
+        self.config.mode = Mode::Forced;
+        delay.delay_us(self.measurement_time_us());
+
+        // The conversion is over, so the burst cannot straddle one
+        Ok(RawMeasurement::from(self.dev.read_burst()?))
+    }
+
+    /// Read the temperature and update t_fine, shared by pressure and humidity compensation.
+    fn read_t_fine(&mut self) -> Result<i32, SensorError<B::Error>> {
+        self.ensure_awake()?;
+        let adc_t = self.dev.get_temperature_raw()?;
+        self.compensate_t_fine(adc_t)
+    }
+
+    /// Compensate a raw temperature, apply the configured offset and store the resulting t_fine.
+    fn compensate_t_fine(&mut self, adc_t: u32) -> Result<i32, SensorError<B::Error>> {
+        self.t_fine = t_fine_from_raw(&self.calibration()?, adc_t, self.config.temperature_offset_c)?;
+        Ok(self.t_fine)
+    }
+
+    /// Compensate a raw pressure into pascal with the current t_fine.
+    fn compensate_pressure(&mut self, adc_p: u32) -> Result<f64, SensorError<B::Error>> {
+        pressure_from_raw(&self.calibration()?, adc_p, self.t_fine)
+    }
+
+    /// Compensate a raw humidity into percent with the current t_fine, NaN without humidity.
+    fn compensate_humidity(&mut self, adc_h: u32) -> Result<f64, SensorError<B::Error>> {
+        humidity_from_raw(&self.calibration()?, adc_h, self.t_fine)
+    }
+
+    /// Restart the filter settling count when `config` changes what the filter averages over.
+    fn track_filter(&mut self, config: &Config) {
+        let previous = self.config;
+        let restarted = !self.configured
+            || previous.filter != config.filter
+            || previous.standby_time != config.standby_time
+            || previous.mode != config.mode;
+        if restarted {
+            self.filter_samples = 0;
+        }
+    }
+
+    /// Fail on variants without the humidity channel.
+    fn ensure_humidity(&self) -> Result<(), SensorError<B::Error>> {
+        match self.variant.has_humidity() {
+            true => Ok(()),
+            false => Err(SensorError::NoHumidity),
+        }
+    }
+
+    /// Fail when the sensor was left in sleep mode, where the data registers only hold the last
+    /// sample or the reset values.
+    fn ensure_awake(&self) -> Result<(), SensorError<B::Error>> {
+        self.ensure_configured()?;
+        match self.config.mode {
+            Mode::Sleep => Err(SensorError::Asleep),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail when no configuration was written or read back since construction or reset, as the
+    /// power-on state skips every channel and the data registers hold meaningless values.
+    fn ensure_configured(&self) -> Result<(), SensorError<B::Error>> {
+        if self.configured {
+            Ok(())
+        } else {
+            Err(SensorError::NotConfigured)
+        }
+    }
+
+    /// Call `callback` after every bus transaction, for protocol debugging, see `Traced`.
+    #[cfg(feature = "trace")]
+    pub fn with_trace<F: FnMut(&TraceEvent)>(self, callback: F) -> AtmosphericSensor<Traced<B, F>> {
+        AtmosphericSensor {
+            #[cfg(not(feature = "sleep-on-drop"))]
+            dev: Traced::new(self.dev, callback),
+            #[cfg(feature = "sleep-on-drop")]
+            dev: self.dev.map(|dev| Traced::new(dev, callback)),
+            calibration: self.calibration,
+            variant: self.variant,
+            config: self.config,
+            configured: self.configured,
+            t_fine: self.t_fine,
+            cache: self.cache,
+            filter_samples: self.filter_samples,
+            sea_level_pa: self.sea_level_pa,
+            watchdog: self.watchdog,
+        }
+    }
+
+    /// Take the bus out of the driver.
+    #[cfg(not(feature = "sleep-on-drop"))]
+    fn into_bus(self) -> B {
+        self.dev
+    }
+
+    /// Take the bus out of the driver, putting the sensor to sleep first like `drop` does.
+    #[cfg(feature = "sleep-on-drop")]
+    fn into_bus(self) -> B {
+        self.dev.into_inner()
+    }
+}
+
+/// Internal state, exposed for integration tests with the `test-util` feature.
+///
+/// The calibration getters panic on a lazy sensor that hasn't read its calibration yet.
+#[cfg(feature = "test-util")]
+impl<B: Bus> AtmosphericSensor<B> {
+    /// Fine temperature shared by pressure and humidity compensation.
+    pub fn t_fine(&self) -> i32 {
+        self.t_fine
+    }
+
+    /// Override the fine temperature used by the next pressure or humidity compensation.
+    pub fn set_t_fine(&mut self, t_fine: i32) {
+        self.t_fine = t_fine;
+    }
+
+    /// Temperature calibration T1 to T3.
+    pub fn temperature_calibration(&self) -> (u16, i16, i16) {
+        self.loaded_calibration().temperature.coefficients()
+    }
+
+    /// Pressure calibration P1, then P2 to P9.
+    pub fn pressure_calibration(&self) -> (u16, [i16; 8]) {
+        self.loaded_calibration().pressure.coefficients()
+    }
+
+    /// Humidity calibration H1 to H6, `None` on variants without humidity.
+    pub fn humidity_calibration(&self) -> Option<(u8, i16, u8, i16, i16, i8)> {
+        self.loaded_calibration().humidity.as_ref().map(|humidity| humidity.coefficients())
+    }
+
+    fn loaded_calibration(&self) -> &Calibration {
+        self.calibration.as_ref().expect("calibration not read yet")
+    }
+}
+
+
+/// Compensate a raw sample, returning it with the t_fine of its temperature.
+///
+/// Skipped pressure and humidity channels come out as NaN. The temperature offset and the
+/// range policy of `config` are applied.
+pub(crate) fn compensate_sample<E>(
+    calibration: &Calibration,
+    raw: &RawMeasurement,
+    config: &Config,
+) -> Result<(Measurements, i32), SensorError<E>> {
+    // Temperature goes first so pressure and humidity use its t_fine.
+    let t_fine = t_fine_from_raw(calibration, raw.temperature, config.temperature_offset_c)?;
+    let temperature_celsius = calibration.temperature_celsius(t_fine);
+    let pressure_pascal = match is_skipped_raw(Channel::Pressure, raw.pressure) {
+        true => f64::NAN,
+        false => pressure_from_raw(calibration, raw.pressure, t_fine)?,
+    };
+    let humidity_percent = match is_skipped_raw(Channel::Humidity, raw.humidity) {
+        true => f64::NAN,
+        false => humidity_from_raw(calibration, raw.humidity, t_fine)?,
+    };
+
+    let policy = config.out_of_range;
+    let sample = Measurements {
+        temperature_celsius: policy.apply(Channel::Temperature, temperature_celsius)?,
+        pressure_pascal: policy.apply(Channel::Pressure, pressure_pascal)?,
+        humidity_percent: policy.apply(Channel::Humidity, humidity_percent)?,
+    };
+    Ok((sample, t_fine))
+}
+
+/// Compensate a raw sample with the double precision formulas.
+///
+/// Skipped channels, the temperature offset and the range policy are handled like in
+/// `compensate_sample`.
+pub(crate) fn compensate_sample_f64<E>(
+    calibration: &Calibration,
+    raw: &RawMeasurement,
+    config: &Config,
+) -> Result<Measurements, SensorError<E>> {
+    ensure_sampled(Channel::Temperature, raw.temperature)?;
+    let t_fine = calibration.temperature.compensate_temperature_f64(raw.temperature as i32)
+        - config.temperature_offset_c * 5120.0;
+    let pressure_pascal = match is_skipped_raw(Channel::Pressure, raw.pressure) {
+        true => f64::NAN,
+        false => calibration.pressure.compensate_pressure_f64(raw.pressure as i32, t_fine),
+    };
+    let humidity_percent = match (&calibration.humidity, is_skipped_raw(Channel::Humidity, raw.humidity)) {
+        (Some(humidity_calibration), false) => humidity_calibration.compensate_humidity_f64(raw.humidity as i32, t_fine),
+        _ => f64::NAN,
+    };
+
+    let policy = config.out_of_range;
+    Ok(Measurements {
+        temperature_celsius: policy.apply(Channel::Temperature, t_fine / 5120.0)?,
+        pressure_pascal: policy.apply(Channel::Pressure, pressure_pascal)?,
+        humidity_percent: policy.apply(Channel::Humidity, humidity_percent)?,
+    })
+}
+
+/// t_fine of a raw temperature, with the offset in celsius subtracted.
+fn t_fine_from_raw<C: Compensation, E>(compensation: &C, adc_t: u32, temperature_offset_c: f64) -> Result<i32, SensorError<E>> {
+    // Nothing can be compensated without a temperature
+    ensure_sampled(Channel::Temperature, adc_t)?;
+    let t_fine = compensation.fine_temperature(adc_t as i32)?;
+    // t_fine counts 1/5120 of a degree
+    let offset = temperature_offset_c * 5120.0;
+    // Round half away from zero, f64::round needs std
+    let offset = (offset + 0.5_f64.copysign(offset)) as i32;
+    Ok(t_fine - offset)
+}
+
+/// Pressure in pascal of a raw pressure.
+fn pressure_from_raw<C: Compensation, E>(compensation: &C, adc_p: u32, t_fine: i32) -> Result<f64, SensorError<E>> {
+    Ok(compensation.pressure_pascal(adc_p as i32, t_fine)?)
+}
+
+/// Relative humidity in percent of a raw humidity, NaN without humidity calibration.
+fn humidity_from_raw<E>(calibration: &Calibration, adc_h: u32, t_fine: i32) -> Result<f64, SensorError<E>> {
+    let Some(humidity_calibration) = &calibration.humidity else {
+        return Ok(f64::NAN);
+    };
+    let humidity = humidity_calibration.compensate_humidity(adc_h as i32, t_fine)?;
+    Ok(f64::from(humidity) / 1024.0)
+}
+
+/// 7-bit bus address of `address`, failing when it doesn't fit.
+pub(crate) fn checked_address<E>(address: Address) -> Result<u8, SensorError<E>> {
+    let address = u8::from(address);
+    match address <= 0x7F {
+        true => Ok(address),
+        false => Err(SensorError::InvalidAddress(address)),
+    }
+}
+
+/// Fail when a raw value is the reset value of its channel.
+fn ensure_sampled<E>(channel: Channel, raw: u32) -> Result<(), SensorError<E>> {
+    match is_skipped_raw(channel, raw) {
+        true => Err(SensorError::Skipped(channel)),
+        false => Ok(()),
+    }
+}
+
+
+/// Round to the nearest integer, halves towards positive infinity.
+///
+/// `f64::round` needs `std`, so this floors `value + 0.5` by hand.
+fn round_half_up(value: f64) -> i32 {
+    let shifted = value + 0.5;
+    let truncated = shifted as i32;
+    match f64::from(truncated) > shifted {
+        true => truncated - 1,
+        false => truncated,
+    }
+}
+
+/// Temperature in tenths of a degree from t_fine, rounded half away from zero.
+///
+/// t_fine counts 1/5120 of a degree, so a tenth is 512 counts. Rounding once from t_fine
+/// avoids the double rounding through the datasheet's centidegrees.
+fn tenths_from_t_fine(t_fine: i32) -> i16 {
+    // Integer division truncates towards zero
+    let rounded = match t_fine < 0 {
+        true => (t_fine - 256) / 512,
+        false => (t_fine + 256) / 512,
+    };
+    rounded as i16
+}
+
+/// Apply the range policy to a display value, judged on the unrounded `reading`.
+///
+/// A clamped reading is converted to display units with `per_unit`. The operating bounds are
+/// whole numbers in every display unit, so rounding only absorbs the error of the scaling.
+fn display_in_range<E>(policy: OutOfRange, channel: Channel, reading: f64, display: i32, per_unit: f64) -> Result<i32, SensorError<E>> {
+    let checked = policy.apply(channel, reading)?;
+    match checked == reading {
+        true => Ok(display),
+        false => Ok(round_half_up(checked * per_unit)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::i2c::{Error, ErrorKind, I2c, NoAcknowledgeSource};
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+    use super::i2c::{self, constants::{values, Register}};
+    #[cfg(feature = "std")]
+    use super::{derived, STANDARD_SEA_LEVEL_PASCAL};
+    use super::{i2c::Address, AtmosphericSensor, AtmosphericSensorI2c, Calibration, registers, ranges, Channel, Config, Filter, LivenessWindow, Measurements, Mode, OutOfRange, Oversampling, Profile, RawMeasurement, SensorError, SensorState, StandbyTime, Status, Variant, CHIP_ID_BMP280, INHG_PER_PASCAL, MMHG_PER_PASCAL};
+
+    /// Flatten single transactions and the transactions of register reads into one list.
+    macro_rules! transactions {
+        ($($step:expr),* $(,)?) => {{
+            let mut steps: Vec<embedded_hal_mock::eh1::i2c::Transaction> = Vec::new();
+            $(crate::tests::Steps::extend_into($step, &mut steps);)*
+            steps
+        }};
+    }
+    pub(crate) use transactions;
+
+    /// Anything `transactions!` takes.
+    pub(crate) trait Steps {
+        fn extend_into(self, steps: &mut Vec<I2cTransaction>);
+    }
+
+    impl Steps for I2cTransaction {
+        fn extend_into(self, steps: &mut Vec<I2cTransaction>) {
+            steps.push(self);
+        }
+    }
+
+    impl Steps for Vec<I2cTransaction> {
+        fn extend_into(self, steps: &mut Vec<I2cTransaction>) {
+            steps.extend(self);
+        }
+    }
+
+    /// Transactions per register read, see `register_read`.
+    pub(crate) const READ_STEPS: usize = if cfg!(feature = "split-write-read") { 2 } else { 1 };
+
+    /// Register read as the mock sees it: one `write_read`, or with `split-write-read` a
+    /// `write` of the register followed by a `read`.
+    pub(crate) fn register_read(address: u8, register: Vec<u8>, response: Vec<u8>) -> Vec<I2cTransaction> {
+        if cfg!(feature = "split-write-read") {
+            vec![I2cTransaction::write(address, register), I2cTransaction::read(address, response)]
+        } else {
+            vec![I2cTransaction::write_read(address, register, response)]
+        }
+    }
+
+    /// Register read that fails on the bus; with `split-write-read` the register write already
+    /// fails and the read is never issued.
+    pub(crate) fn failed_read(address: u8, register: Vec<u8>, response: Vec<u8>, error: ErrorKind) -> Vec<I2cTransaction> {
+        if cfg!(feature = "split-write-read") {
+            vec![I2cTransaction::write(address, register).with_error(error)]
+        } else {
+            vec![I2cTransaction::write_read(address, register, response).with_error(error)]
+        }
+    }
+
+    #[test]
+    fn read_humidity() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::HUMIDITY_MSB_REG], vec![110, 213]);
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        // sensor.t_fine = 0;
+        let humidity = sensor.get_humidity_relative().unwrap();
+        
+        assert!(humidity - 46.159 < 0.1);
+
+        finish(sensor, mock);
+        
+    }
+
+    #[test]
+    fn read_humidity_only() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![128, 189, 0, 110, 213]);
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let humidity = sensor.read_humidity_only().unwrap();
+
+        // Same as compensating the full sample
+        let raw = RawMeasurement::from([0, 0, 0, 128, 189, 0, 110, 213]);
+        assert_eq!(humidity, sensor.compensate(&raw).unwrap().humidity_percent);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_temperature() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![0, 0, 0]);
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        sensor.t_fine = 0;
+        let temperature = sensor.get_temperature_celsius().unwrap();
+
+        assert!(temperature > -100.);
+        assert!(temperature < 100.);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_temperature_precise() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![128, 189, 0]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let rounded = sensor.get_temperature_celsius().unwrap();
+        let precise = sensor.get_temperature_celsius_precise().unwrap();
+
+        // Same sample, only the rounding to centidegrees differs
+        assert!((rounded - precise).abs() <= 0.005);
+        assert_eq!(precise, f64::from(sensor.t_fine) / 5120.0);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn temperature_units() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..3 {
+            expectations.extend(
+                register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![128, 189, 0]),
+            );
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let celsius = sensor.get_temperature_celsius().unwrap();
+        assert!((sensor.get_temperature_fahrenheit().unwrap() - (celsius * 9.0 / 5.0 + 32.0)).abs() < 1e-9);
+        assert!((sensor.get_temperature_kelvin().unwrap() - (celsius + 273.15)).abs() < 1e-9);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn error_display() {
+        let error = SensorError::I2c(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        assert_eq!(error.to_string(), "I2C error: NoAcknowledge(Address)");
+        assert_eq!(SensorError::<ErrorKind>::InvalidChipId(0x61).to_string(), "Unknown chip ID 0x61");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn error_boxing() {
+        // Host side tools can propagate it with `?` into a boxed error
+        fn host() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Err(SensorError::<ErrorKind>::Timeout)?
+        }
+        assert_eq!(host().unwrap_err().to_string(), "Timed out waiting for the sensor");
+    }
+
+    #[test]
+    fn skipped_channels() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![0x80, 0x00, 0x00]),
+            register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![0x80, 0x00, 0x00]),
+            register_read(address, vec![registers::HUMIDITY_MSB_REG], vec![0x80, 0x00]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        assert_eq!(sensor.get_pressure_pascal().unwrap_err(), SensorError::Skipped(Channel::Pressure));
+        assert_eq!(sensor.get_temperature_celsius().unwrap_err(), SensorError::Skipped(Channel::Temperature));
+        assert_eq!(sensor.get_humidity_relative().unwrap_err(), SensorError::Skipped(Channel::Humidity));
+        assert_eq!(SensorError::<ErrorKind>::Skipped(Channel::Humidity).to_string(), "Humidity channel skipped");
+
+        // Humidity sensing preset: pressure skipped
+        let sample = sensor.compensate(&RawMeasurement::from([0x80, 0, 0, 128, 189, 0, 110, 213])).unwrap();
+        assert!(sample.pressure_pascal.is_nan());
+        assert!(!sample.humidity_percent.is_nan());
+        // Without temperature nothing can be compensated
+        let unsampled = RawMeasurement::from([82, 79, 0, 0x80, 0, 0, 110, 213]);
+        assert_eq!(sensor.compensate(&unsampled).unwrap_err(), SensorError::Skipped(Channel::Temperature));
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_pressure() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::PRESSURE_MSB_REG], vec![0, 0, 0]);
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        sensor.t_fine = 0;
+        let pressure = sensor.get_pressure_pascal().unwrap();
+
+        assert!(pressure > 0.0);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn pressure_units() {
+        // One standard atmosphere
+        assert!((101325.0 * MMHG_PER_PASCAL - 760.0).abs() < 0.01);
+        assert!((101325.0 * INHG_PER_PASCAL - 29.92).abs() < 0.01);
+
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..5 {
+            expectations.extend(
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0]),
+            );
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        sensor.t_fine = 116770;
+        let pascal = sensor.get_pressure_pascal().unwrap();
+        assert!((sensor.get_pressure_hpa().unwrap() - pascal / 100.0).abs() < 1e-9);
+        assert!((sensor.get_pressure_kpa().unwrap() - pascal / 1000.0).abs() < 1e-9);
+        assert!((sensor.get_pressure_mmhg().unwrap() - pascal * 0.00750062).abs() < 1e-9);
+        assert!((sensor.get_pressure_inhg().unwrap() - pascal * 0.0002953).abs() < 1e-9);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn rounded_units() {
+        assert_eq!(super::round_half_up(1013.5), 1014);
+        assert_eq!(super::round_half_up(1013.49), 1013);
+        assert_eq!(super::round_half_up(-2.5), -2);
+        assert_eq!(super::round_half_up(-2.51), -3);
+        assert_eq!(super::round_half_up(-0.4), 0);
+
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..3 {
+            expectations.extend(
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0]),
+            );
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        sensor.t_fine = 116770;
+        let pascal = sensor.get_pressure_pascal().unwrap();
+        let rounded = sensor.get_pressure_pascal_rounded().unwrap();
+        assert!((f64::from(rounded) - pascal).abs() <= 0.5);
+        let hpa = sensor.get_pressure_hpa_rounded().unwrap();
+        assert!((f64::from(hpa) - pascal / 100.0).abs() <= 0.5);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_weather() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let weather = sensor.read_weather(101325.0).unwrap();
+
+        assert!(weather.dew_point_celsius <= weather.temperature_celsius);
+        assert_eq!(weather.altitude_meters, derived::altitude_meters(weather.pressure_pascal, 101325.0));
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn pressure_sea_level() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..4 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let station = sensor.measure().unwrap().pressure_pascal;
+
+        // At 340 m the station pressure is about 4% below sea level
+        let sea_level = sensor.get_pressure_sea_level_pa(340.0).unwrap();
+        assert!((station / sea_level - 0.96035).abs() < 1e-5);
+        assert_eq!(sensor.get_pressure_sea_level_pa(44330.0), Err(SensorError::AltitudeOutOfRange));
+        assert!(matches!(sensor.calibrate_altitude(50000.0), Err(SensorError::AltitudeOutOfRange)));
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn absolute_humidity() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let sample = sensor.measure().unwrap();
+        assert_eq!((sample.temperature_celsius, sample.humidity_percent), (91.5, 46.8671875));
+
+        // Worked by hand for 91.5 celsius at 46.87%, measured again
+        let absolute = sensor.get_absolute_humidity().unwrap();
+        assert!((absolute - 212.3946).abs() < 1e-4);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn calibrate_altitude() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        assert_eq!(sensor.sea_level_pressure_pa(), STANDARD_SEA_LEVEL_PASCAL);
+
+        let sea_level = sensor.calibrate_altitude(250.0).unwrap();
+        assert_eq!(sensor.sea_level_pressure_pa(), sea_level);
+        // Same pressure again reads back as the known altitude
+        assert!((sensor.get_altitude_meters().unwrap() - 250.0).abs() < 1e-6);
+
+        sensor.set_sea_level_pressure_pa(102000.0);
+        assert_eq!(sensor.sea_level_pressure_pa(), 102000.0);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_display_tuple() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..3 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let sample = sensor.measure().unwrap();
+        let (tenths, hectopascal, percent) = sensor.read_display_tuple().unwrap();
+
+        assert_eq!(tenths, (sample.temperature_celsius * 10.0).round() as i16);
+        assert_eq!(hectopascal, (sample.pressure_pascal / 100.0).round() as u16);
+        assert_eq!(percent, sample.humidity_percent.round() as u8);
+
+        // Offset the temperature down to t_fine 60135, 11.745 degrees: rounding through
+        // centidegrees would give 11.8
+        sensor.config.temperature_offset_c = f64::from(sensor.t_fine - 60135) / 5120.0;
+        let (tenths, _, _) = sensor.read_display_tuple().unwrap();
+        assert_eq!(sensor.t_fine, 60135);
+        assert_eq!(tenths, 117);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn tenths_round_half_away_from_zero() {
+        // 25.05 and -25.05 degrees
+        assert_eq!(super::tenths_from_t_fine(128256), 251);
+        assert_eq!(super::tenths_from_t_fine(-128256), -251);
+        // 25.04 degrees
+        assert_eq!(super::tenths_from_t_fine(128205), 250);
+        // 11.745 degrees, 117.45 tenths
+        assert_eq!(super::tenths_from_t_fine(60135), 117);
+        assert_eq!(super::tenths_from_t_fine(-60135), -117);
+    }
+
+    #[test]
+    fn out_of_range_policy() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..5 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let raw = sensor.measure().unwrap();
+        // The mock calibration puts the temperature above the operating range
+        assert!(!ranges::TEMPERATURE_CELSIUS.contains(&raw.temperature_celsius));
+
+        sensor.config.out_of_range = OutOfRange::Clamp;
+        let clamped = sensor.measure().unwrap();
+        assert_eq!(clamped.temperature_celsius, *ranges::TEMPERATURE_CELSIUS.end());
+        assert_eq!(clamped.pressure_pascal, raw.pressure_pascal);
+
+        let (tenths, hectopascal, _) = sensor.read_display_tuple().unwrap();
+        assert_eq!(tenths, 850);
+        assert_eq!(hectopascal, (raw.pressure_pascal / 100.0).round() as u16);
+
+        sensor.config.out_of_range = OutOfRange::Error;
+        assert_eq!(sensor.measure().unwrap_err(), SensorError::OutOfRange(Channel::Temperature));
+        assert_eq!(sensor.read_display_tuple().unwrap_err(), SensorError::OutOfRange(Channel::Temperature));
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn state() {
+        let address: u8 = Address::Default.into();
+        let normal = Config::default().mode(Mode::Normal);
+        let forced = Config::default().mode(Mode::Forced);
+        let expectations = transactions![
+            // Normal mode, measuring, then idle
+            config_writes(&normal),
+            register_read(address, vec![registers::STAT_REG], vec![0x08, 0x27]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00, 0x27]),
+            // Reset behind the driver's back
+            register_read(address, vec![registers::STAT_REG], vec![0x00, 0x00]),
+            // Forced, the running sensor is put to sleep first
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x00]),
+            config_writes(&forced),
+            // Forced measurement done, back to sleep
+            register_read(address, vec![registers::STAT_REG], vec![0x00, 0x24]),
+            // Sleep, while copying the calibration from NVM
+            config_writes(&Config::default()),
+            register_read(address, vec![registers::STAT_REG], vec![0x01, 0x00]),
+        ];
+        let (mut sensor, mock) = mock_sensor(expectations);
+        assert_eq!(sensor.state().unwrap(), SensorState::Uninitialized);
+        sensor.apply_config(&normal).unwrap();
+        assert_eq!(sensor.state().unwrap(), SensorState::Measuring);
+        assert_eq!(sensor.state().unwrap(), SensorState::Ready);
+        assert_eq!(sensor.state().unwrap(), SensorState::Fault);
+        sensor.apply_config(&forced).unwrap();
+        assert_eq!(sensor.state().unwrap(), SensorState::Ready);
+        sensor.apply_config(&Config::default()).unwrap();
+        assert_eq!(sensor.state().unwrap(), SensorState::Uninitialized);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_air_density() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let density = sensor.get_air_density_kgm3().unwrap();
+
+        let raw = RawMeasurement::from([82, 79, 0, 128, 189, 0, 110, 213]);
+        let sample = sensor.compensate(&raw).unwrap();
+        assert_eq!(density, derived::air_density_kgm3(sample.temperature_celsius, sample.pressure_pascal, sample.humidity_percent));
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn derived_quantities_share_a_sample() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        // Only the first call reads the bus
+        let dew_point = sensor.get_dew_point_celsius().unwrap();
+        let absolute = sensor.get_absolute_humidity_gm3().unwrap();
+        sensor.get_air_density_kgm3().unwrap();
+        sensor.get_heat_index_celsius().unwrap();
+        assert_eq!(sensor.humidity_at_temperature(dew_point).unwrap(), derived::relative_humidity_percent(absolute, dew_point));
+
+        sensor.invalidate_cache();
+        assert_eq!(sensor.get_dew_point_celsius().unwrap(), dew_point);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn filter_settling() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config { mode: Mode::Normal, filter: Filter::C2, ..Config::default() }, expectations);
+        assert_eq!(sensor.filter_discard_count(), 2);
+        sensor.measure().unwrap();
+        assert!(!sensor.filter_settled());
+        sensor.measure().unwrap();
+        assert!(sensor.filter_settled());
+
+        // Same filter, standby time and mode keep the filter warm
+        sensor.track_filter(&Config { temperature_oversampling: Oversampling::Ox2, ..sensor.config });
+        assert!(sensor.filter_settled());
+        sensor.track_filter(&Config { standby_time: StandbyTime::Ms1000, ..sensor.config });
+        assert_eq!(sensor.filter_discard_count(), 2);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn configure_low_power() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        // A full configuration takes three writes and no reads
+        expectations.extend([
+            // 1x humidity
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x01]),
+            // Standby 1000ms and filter off
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0xA0]),
+            // 1x temperature and pressure, normal mode, latching the humidity setting
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x27]),
+        ]);
+        let (mut sensor, mock) = mock_sensor(expectations);
+        sensor.configure_low_power().unwrap();
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn with_config() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = transactions![register_read(address, vec![registers::CHIP_ID_REG], vec![0x60])];
+        expectations.extend(get_mock_calibration(address));
+        expectations.extend([
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x01]),
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0xA0]),
+            // Left asleep, as configured
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x24]),
+        ]);
+        expectations.extend(drop_sleep(address));
+
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
+
+        let config = Config { mode: Mode::Sleep, ..Config::low_power() };
+        let sensor = AtmosphericSensor::with_config(i2c, Address::Default, config).unwrap();
+        assert_eq!(sensor.config, config);
+        assert!(sensor.configured);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn apply_profile() {
+        let address: u8 = Address::Default.into();
+        let expectations = vec![
+            // Humidity skipped
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x00]),
+            // Standby 0.5ms and filter 16
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0x10]),
+            // 1x temperature, 4x pressure and normal mode
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x2F]),
+        ];
+        let (mut sensor, mock) = mock_sensor(expectations);
+        sensor.config.temperature_offset_c = 1.5;
+        sensor.apply_profile(Profile::Gaming).unwrap();
+        assert_eq!(sensor.config, Config { temperature_offset_c: 1.5, ..Config::gaming() });
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn apply_config_verified() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for (read_back, running) in [(0x27, false), (0x23, true)] {
+            if running {
+                // Put to sleep before the config write
+                expectations.push(I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x24]));
+            }
+            expectations.extend([
+                I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x01]),
+                I2cTransaction::write(address, vec![registers::CONFIG_REG, 0xA0]),
+                I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x27]),
+            ]);
+            // spi3w_en set in config is ignored
+            expectations.extend(
+                register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01, 0x00, read_back, 0xA1]),
+            );
+        }
+        let (mut sensor, mock) = mock_sensor(expectations);
+        sensor.apply_config_verified(&Config::low_power()).unwrap();
+        // Pressure oversampling lost on the way
+        let error = sensor.apply_config_verified(&Config::low_power()).unwrap_err();
+        assert_eq!(error, SensorError::ConfigMismatch { register: registers::CTRL_MEAS_REG, written: 0x27, read: 0x23 });
+        assert_eq!(error.to_string(), "Config mismatch on register 0xf4: wrote 0x27, read 0x23");
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn apply_config_minimal() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            // Already in place
+            register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01, 0x00, 0x27, 0xA0]),
+            // Filter changed: sleep, config, then normal mode again
+            register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01, 0x00, 0x27, 0xA0]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x24]),
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0xA4]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x27]),
+            // Humidity changed: ctrl_meas written again so it takes effect
+            register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01, 0x00, 0x27, 0xA0]),
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x05]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x27]),
+        ];
+        let (mut sensor, mock) = mock_sensor(expectations);
+        let low_power = Config::low_power();
+        assert_eq!(sensor.apply_config_minimal(&low_power).unwrap(), 0);
+        assert_eq!(sensor.apply_config_minimal(&Config { filter: Filter::C2, ..low_power }).unwrap(), 3);
+        let humid = Config { humidity_oversampling: Oversampling::Ox16, ..low_power };
+        assert_eq!(sensor.apply_config_minimal(&humid).unwrap(), 2);
+        assert_eq!(sensor.config, humid);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measure_ignores_stale_t_fine() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        // Left by a read of another, much colder sample
+        sensor.t_fine = -100000;
+        let sample = sensor.measure().unwrap();
+        let fresh_t_fine = sensor.t_fine;
+        let expected = sensor.compensate(&RawMeasurement::from([82, 79, 0, 128, 189, 0, 110, 213])).unwrap();
+        assert_eq!(sample, expected);
+        assert_eq!(sensor.t_fine, fresh_t_fine);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measure_f64() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let sample = sensor.measure_f64().unwrap();
+        let integer = sensor.compensate(&RawMeasurement::from([82, 79, 0, 128, 189, 0, 110, 213])).unwrap();
+        assert!((sample.temperature_celsius - integer.temperature_celsius).abs() < 0.01);
+        assert!((sample.pressure_pascal - integer.pressure_pascal).abs() < 1.0);
+        assert!((sample.humidity_percent - integer.humidity_percent).abs() < 0.01);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measure_retries_torn_burst() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            // A conversion finishes during the first burst
+            register_read(address, vec![registers::STAT_REG], vec![0x08]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![0, 0, 0, 0, 0, 0, 0, 0]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            // Second burst is stable
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+            register_read(address, vec![registers::STAT_REG], vec![0x08]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let sample = sensor.measure().unwrap();
+        assert!(sample.pressure_pascal > 0.0);
+
+        finish(sensor, mock);
+    }
 
-// Local imports
-use calibration::Calibration;
-use i2c::AtmosphericSensorI2c;
-pub use i2c::Address;
+    #[test]
+    #[cfg(feature = "trace")]
+    fn trace_transactions() {
+        use super::Direction;
 
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::CTRL_MEAS_REG], vec![0x27]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x24]),
+        ];
+        let (sensor, mock) = mock_sensor(expectations);
 
-/// Atmospheric sensor
-pub struct AtmosphericSensor<I2C> {
-    dev: AtmosphericSensorI2c<I2C>,
-    calibration: Calibration,
-    t_fine: i32,
-}
+        // The callback captures a buffer
+        let mut events = Vec::new();
+        let mut sensor = sensor.with_trace(|event| events.push((event.direction, event.register, event.bytes.to_vec(), event.ok)));
+        sensor.stop().unwrap();
 
-impl<I2C: I2c> AtmosphericSensor<I2C> {
-    /// Create new AtmosphericSensor device wrapper for I2C communication.
-    pub fn new(dev: I2C, address: Address) -> AtmosphericSensor<I2C> {
-        let mut wrapper = AtmosphericSensorI2c::new(dev, address.into());
-        let calibration = calibration::Calibration::build(&mut wrapper);
-        AtmosphericSensor { dev: wrapper, calibration: calibration, t_fine: 0 }
+        finish(sensor, mock);
+        assert_eq!(events[..2], [
+            (Direction::Read, registers::CTRL_MEAS_REG, vec![0x27], true),
+            (Direction::Write, registers::CTRL_MEAS_REG, vec![0x24], true),
+        ]);
     }
 
-    /// Create new AtmosphericSensor and start it.
-    pub fn build(dev: I2C, address: Address) -> AtmosphericSensor<I2C> {
-        let mut sensor = AtmosphericSensor::new(dev, address.into());
-        sensor.start().unwrap();
-        sensor
+    #[test]
+    fn measurement_time() {
+        let indoor_navigation = Config::indoor_navigation();
+        let (_, ctrl_meas, _) = indoor_navigation.to_registers();
+        let expectations = transactions![
+            // Put to sleep before the second configuration
+            I2cTransaction::write(Address::Default.into(), vec![registers::CTRL_MEAS_REG, ctrl_meas & 0xFC]),
+            config_writes(&indoor_navigation),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::low_power(), expectations);
+        assert_eq!(sensor.measurement_time_us(), 9300);
+        assert_eq!(sensor.max_measurement_time_ms(), 10);
+        sensor.apply_config(&indoor_navigation).unwrap();
+        assert_eq!(sensor.measurement_time_us(), 46100);
+        assert_eq!(sensor.max_measurement_time_ms(), 47);
+        assert_eq!(sensor.startup_time_us(), 48100);
+
+        finish(sensor, mock);
     }
 
-    /// Start all parameters from for the sensor
-    pub fn start(&mut self) -> Result<(), String> {
-        self.dev.set_standby_time(i2c::StandyTime::Ms0_5);
-        self.dev.set_filter(i2c::Filter::Off);
-        self.dev.set_temperature_oversample(i2c::Oversampling::Ox1);
-        self.dev.set_pressure_oversample(i2c::Oversampling::Ox1);
-        self.dev.set_humidity_oversample(i2c::Oversampling::Ox1);
-        self.dev.set_mode(i2c::Mode::Normal);
-        Ok(())
+    #[test]
+    fn read_temperature_unfiltered() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            // Sleep before touching the filter
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x54]),
+            // Forced measurement with filter off
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x01]),
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0x00]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x55]),
+            register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![128, 189, 0]),
+            // Previous configuration restored
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x01]),
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0x10]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x57]),
+        ];
+        let (mut sensor, mock) = configured_sensor(&Config::indoor_navigation(), expectations);
+        sensor.get_temperature_celsius_unfiltered(&mut NoopDelay::new()).unwrap();
+        assert_eq!(sensor.config, Config::indoor_navigation());
+
+        finish(sensor, mock);
     }
 
-    /// Stop the sensor.
-    pub fn stop(&mut self) -> Result<(), String> {
-        self.dev.set_mode(i2c::Mode::Sleep);
-        Ok(())
+    #[test]
+    fn snapshot_config() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01, 0x00, 0x27, 0xA0]);
+        let (mut sensor, mock) = mock_sensor(expectations);
+        let config = sensor.snapshot_config().unwrap();
+        assert_eq!(config, Config::low_power());
+        assert_eq!(sensor.config, Config::low_power());
+
+        finish(sensor, mock);
     }
 
-    /// Reset device.
-    pub fn reset(&mut self) -> Result<(), String> {
-        self.dev.reset();
-        Ok(())
+    #[test]
+    fn status_bus_fault() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            failed_read(address, vec![registers::STAT_REG], vec![0x00], ErrorKind::Other),
+            failed_read(address, vec![registers::STAT_REG], vec![0x00], ErrorKind::Other),
+        ];
+
+        // A failed status read is an error, not "not measuring"
+        let (mut sensor, mock) = mock_sensor(expectations);
+        assert!(sensor.is_measuring().is_err());
+        assert!(sensor.is_updating().is_err());
+
+        finish(sensor, mock);
     }
 
-    /// Is the device measuring.
-   pub fn is_measuring(&mut self) -> Result<bool, String> {
-        Ok(self.dev.is_measuring())
+    #[test]
+    fn calibration_bus_fault() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = get_mock_calibration(address);
+        // NAK halfway through the pressure calibration
+        expectations.truncate(8 * READ_STEPS);
+        expectations.extend(
+            failed_read(address, vec![registers::DIG_P2_LSB_REG], vec![0x00], ErrorKind::Other)
+        );
+
+        let i2c = I2cMock::new(&expectations);
+        let mut mock = i2c.clone();
+
+        assert_eq!(AtmosphericSensor::new(i2c, Address::Default).err(), Some(SensorError::I2c(ErrorKind::Other)));
+
+        mock.done();
     }
 
-    /// Is the device copying NVM data to image registers.
-    pub fn is_updating(&mut self) -> Result<bool, String> {
-        Ok(self.dev.is_updating())
+    #[test]
+    fn with_calibration() {
+        let address: u8 = Address::Default.into();
+        let (mut sensor, mock) = mock_sensor(Vec::new());
+        let calibration = sensor.calibration().unwrap();
+        assert!(calibration.humidity.is_some());
+        finish(sensor, mock);
+
+        // No bus traffic with a stored calibration, until the sensors are dropped
+        let mut expectations = drop_sleep(address);
+        expectations.extend(drop_sleep(address));
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
+        let mut sensor = AtmosphericSensor::with_calibration(i2c, Address::Default, calibration).unwrap();
+        assert_eq!(sensor.calibration(), Ok(calibration));
+        assert_eq!(sensor.variant(), Variant::Bme280);
+        drop(sensor);
+
+        let without_humidity = Calibration { humidity: None, ..calibration };
+        let sensor = AtmosphericSensor::from_bus_with_calibration(AtmosphericSensorI2c::new(mock.clone(), address), without_humidity);
+        assert_eq!(sensor.variant(), Variant::Bmp280);
+        finish(sensor, mock);
     }
 
-    /// Get temperature in celsius from sensor.
-    pub fn get_temperature_celsius(&mut self) -> Result<f64, String> {
-        let adc_t = self.dev.get_temperature_raw();
-        self.t_fine = self.calibration.temperature.compensate_temperature(adc_t as i32);
-        let output = (self.t_fine * 5 + 128) >> 8;
-        Ok(f64::from(output) / 100.0)
+    #[test]
+    fn new_lazy() {
+        let address: u8 = Address::Default.into();
+        let normal = Config::default().mode(Mode::Normal);
+        let temperature_read = register_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![128, 189, 0]);
+
+        // Nothing on the bus but the configuration until the first read, which then loads the
+        // calibration
+        let expectations = transactions![
+            config_writes(&normal),
+            temperature_read.clone(),
+            get_mock_calibration(address),
+            drop_sleep(address),
+        ];
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
+        let mut lazy = AtmosphericSensor::new_lazy(i2c, Address::Default).unwrap();
+        lazy.apply_config(&normal).unwrap();
+        let temperature = lazy.get_temperature_celsius().unwrap();
+        finish(lazy, mock);
+
+        let (mut eager, mock) = configured_sensor(&normal, temperature_read);
+        assert_eq!(eager.get_temperature_celsius().unwrap(), temperature);
+        finish(eager, mock);
     }
 
-    /// Get pressure in pascal from sensor.
-    pub fn get_pressure_pascal(&mut self) -> Result<f64, String> {
-        let adc_p = self.dev.get_pressure_raw();
-        let pressure = self.calibration.pressure.compensate_pressure(adc_p as i32, self.t_fine);
-        Ok(f64::from(pressure) / 256.0)
+    #[test]
+    fn new_lazy_bus_fault() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = transactions![
+            failed_read(address, vec![registers::DIG_T1_LSB_REG], vec![0x00], ErrorKind::Other),
+        ];
+        expectations.extend(drop_sleep(address));
+
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
+
+        // The dead bus only shows up once the calibration is needed
+        let mut sensor = AtmosphericSensor::new_lazy(i2c, Address::Default).unwrap();
+        assert_eq!(sensor.calibration().err(), Some(SensorError::I2c(ErrorKind::Other)));
+
+        finish(sensor, mock);
     }
 
-    pub fn get_humidity_relative(&mut self) -> Result<f64, String> {
-        let adc_h = self.dev.get_humidity_raw();
-        let humidity = self.calibration.humidity.compensate_humidity(adc_h as i32, self.t_fine);
+    #[test]
+    fn register_access_bus_error() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            failed_read(address, vec![registers::CHIP_ID_REG], vec![0x00], ErrorKind::Other),
+            I2cTransaction::write(address, vec![registers::RST_REG, values::SOFT_RESET]).with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        ];
+
+        let i2c = I2cMock::new(&expectations);
+        let mut mock = i2c.clone();
+        let mut bus = AtmosphericSensorI2c::new(i2c, address);
+
+        // The bus error comes back untouched, its kind can be matched on directly
+        let mut buffer = [0u8];
+        assert_eq!(i2c::read_from_register(&mut bus, Register::ChipId, &mut buffer), Err(ErrorKind::Other));
+        let error = i2c::write_to_register(&mut bus, Register::Reset, values::SOFT_RESET).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
 
-        Ok(f64::from(humidity) / 1024.0)
+        mock.done();
     }
 
-}
+    #[test]
+    fn self_test_failures() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = transactions![
+            // A BMP280 behind a driver set up for a BME280
+            register_read(address, vec![registers::CHIP_ID_REG], vec![CHIP_ID_BMP280]),
+            register_read(address, vec![registers::CHIP_ID_REG], vec![0x60]),
+        ];
+        let blank = Calibration::from_bytes(&[0x00; 26], Some(&[0x00; 7]));
+        expectations.extend(drop_sleep(address));
 
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
 
-#[cfg(test)]
-mod tests {
-    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+        let mut sensor = AtmosphericSensor::with_calibration(i2c, Address::Default, blank).unwrap();
+        let mut delay = RecordingDelay::default();
+        assert_eq!(sensor.self_test(&mut delay), Err(SensorError::InvalidChipId(CHIP_ID_BMP280)));
+        assert_eq!(sensor.self_test(&mut delay), Err(SensorError::CalibrationBlank));
 
-    use super::{i2c::Address, AtmosphericSensor, i2c::constants::registers};
+        finish(sensor, mock);
+    }
 
     #[test]
-    fn read_humidity() {
+    fn build_bus_fault() {
         let address: u8 = Address::Default.into();
         let mut expectations = get_mock_calibration(address);
+        // NAK on the first configuration write
         expectations.push(
-            I2cTransaction::write_read(address, vec![0xFD], vec![110]),    
-        );
-        expectations.push(
-            I2cTransaction::write_read(address, vec![0xFE], vec![213]),
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, 0x01]).with_error(ErrorKind::Other),
         );
+        expectations.extend(drop_sleep(address));
 
         let i2c = I2cMock::new(&expectations);
-        let mut i2c_clone = i2c.clone();
+        let mut mock = i2c.clone();
 
-        let mut sensor = AtmosphericSensor::new(i2c, Address::Default); // = AtmosphericSensor::build(i2c, addresses::DEFAULT);
-        // sensor.t_fine = 0;
-        let humidity = sensor.get_humidity_relative().unwrap();
-        
-        assert!(humidity - 46.159 < 0.1);
+        assert_eq!(AtmosphericSensor::build(i2c, Address::Default).err(), Some(SensorError::I2c(ErrorKind::Other)));
 
-        // Stop i2c
-        i2c_clone.done();
-        
+        mock.done();
     }
 
     #[test]
-    fn read_temperature() {
+    fn measure_forced() {
         let address: u8 = Address::Default.into();
-        let mut expectations = get_mock_calibration(address);
-        expectations.push(
-            I2cTransaction::write_read(address, vec![registers::TEMPERATURE_MSB_REG], vec![0])
+        let mut expectations = Vec::new();
+        expectations.extend(rmw(address, registers::CTRL_MEAS_REG, 0x24, 0x25));
+        expectations.extend(
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
         );
-        expectations.push(
-            I2cTransaction::write_read(address, vec![registers::TEMPERATURE_LSB_REG], vec![0])
+        let (mut sensor, mock) = configured_sensor(&Config { mode: Mode::Sleep, ..Config::weather_monitoring() }, expectations);
+        let mut delay = RecordingDelay::default();
+        let sample = sensor.measure_forced(&mut delay).unwrap();
+        assert!(sample.pressure_pascal > 0.0);
+        assert_eq!(sensor.config.mode, Mode::Forced);
+        // 1x on every channel
+        assert_eq!(delay.total_ns, 9_300_000);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measure_averaged() {
+        let address: u8 = Address::Default.into();
+        let frames: [[u8; 8]; 3] = [
+            [82, 79, 0, 128, 189, 0, 110, 213],
+            // Pressure spike
+            [90, 0, 0, 128, 190, 0, 110, 220],
+            [82, 80, 0, 128, 191, 0, 110, 227],
+        ];
+        let mut expectations = Vec::new();
+        for frame in frames {
+            expectations.extend(rmw(address, registers::CTRL_MEAS_REG, 0x24, 0x25));
+            expectations.extend(register_read(address, vec![registers::PRESSURE_MSB_REG], frame.to_vec()));
+        }
+        let (mut sensor, mock) = configured_sensor(&Config { mode: Mode::Sleep, ..Config::weather_monitoring() }, expectations);
+        let calibration = sensor.calibration().unwrap();
+        let expected: Vec<Measurements> = frames.iter()
+            .map(|frame| super::compensate_sample::<ErrorKind>(&calibration, &RawMeasurement::from(*frame), &sensor.config).unwrap().0)
+            .collect();
+
+        let mut delay = RecordingDelay::default();
+        let averaged = sensor.measure_averaged(3, &mut delay).unwrap();
+        let mean = |value: fn(&Measurements) -> f64| expected.iter().map(value).sum::<f64>() / 3.0;
+        assert!((averaged.temperature_celsius - mean(|sample| sample.temperature_celsius)).abs() < 1e-9);
+        assert!((averaged.humidity_percent - mean(|sample| sample.humidity_percent)).abs() < 1e-9);
+        // The spike is rejected
+        let mut pressures: Vec<f64> = expected.iter().map(|sample| sample.pressure_pascal).collect();
+        pressures.sort_by(f64::total_cmp);
+        assert_eq!(averaged.pressure_pascal, pressures[1]);
+        assert_ne!(averaged.pressure_pascal, expected[1].pressure_pascal);
+        // Three conversions at 1x on every channel
+        assert_eq!(delay.total_ns, 3 * 9_300_000);
+        assert_eq!(sensor.cache, Some(averaged));
+
+        assert_eq!(sensor.measure_averaged(0, &mut delay), Err(SensorError::NoSamples));
+        assert_eq!(delay.total_ns, 3 * 9_300_000);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn temperature_offset() {
+        let (mut sensor, mock) = mock_sensor(Vec::new());
+        let raw = RawMeasurement::from([82, 79, 0, 128, 189, 0, 110, 213]);
+        let untrimmed = sensor.compensate(&raw).unwrap();
+
+        sensor.config.temperature_offset_c = 1.5;
+        let trimmed = sensor.compensate(&raw).unwrap();
+
+        assert!((untrimmed.temperature_celsius - trimmed.temperature_celsius - 1.5).abs() < 0.011);
+        // Pressure and humidity are compensated with the trimmed temperature
+        assert_ne!(untrimmed.pressure_pascal, trimmed.pressure_pascal);
+        assert_ne!(untrimmed.humidity_percent, trimmed.humidity_percent);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_raw_bytes() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]);
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        assert_eq!(sensor.read_raw_bytes().unwrap(), [82, 79, 0, 128, 189, 0, 110, 213]);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_many() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        // The third sample fails on the bus
+        expectations.extend(
+            failed_read(address, vec![registers::STAT_REG], vec![0x00], ErrorKind::Other),
         );
-        expectations.push(
-            I2cTransaction::write_read(address, vec![registers::TEMPERATURE_XLSB_REG], vec![0])
+        let (mut sensor, mock) = configured_sensor(&Config::low_power(), expectations);
+        let mut delay = RecordingDelay::default();
+        let mut out = [Measurements::default(); 4];
+        assert_eq!(sensor.read_many(&mut out, &mut delay).unwrap(), 2);
+        assert_eq!(out[0], out[1]);
+        assert_eq!(out[2], Measurements::default());
+        // Two waits of one output data period, 1x measurement and 1000ms standby
+        assert_eq!(delay.total_ns, 2 * 1_009_300_000);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measurements() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..2 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::low_power(), expectations);
+        let mut delay = RecordingDelay::default();
+        let samples: Vec<_> = sensor.measurements(&mut delay).take(2).collect();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0], samples[1]);
+        assert!(samples[0].is_ok());
+        // One output data period before each read
+        assert_eq!(delay.total_ns, 2 * 1_009_300_000);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measure_watched() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        for _ in 0..3 {
+            expectations.extend(transactions![
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+                register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 110, 213]),
+                register_read(address, vec![registers::STAT_REG], vec![0x00]),
+            ]);
+        }
+        let (mut sensor, mock) = configured_sensor(&Config::default().mode(Mode::Normal), expectations);
+        let mut window = LivenessWindow::new(1);
+        assert!(sensor.measure_watched(&mut window).is_ok());
+        assert!(sensor.measure_watched(&mut window).is_ok());
+        assert_eq!(sensor.measure_watched(&mut window).unwrap_err(), SensorError::Stuck);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn channel_toggles() {
+        let address: u8 = Address::Default.into();
+        // Reserved ctrl_hum bits and the rest of ctrl_meas are kept
+        let expectations = transactions![
+            rmw(address, registers::CTRL_HUMIDITY_REG, 0xA2, 0xA0),
+            rmw(address, registers::CTRL_MEAS_REG, 0x57, 0x57),
+            rmw(address, registers::CTRL_MEAS_REG, 0x57, 0x43),
+            rmw(address, registers::CTRL_HUMIDITY_REG, 0xA0, 0xA5),
+            rmw(address, registers::CTRL_MEAS_REG, 0x43, 0x43),
+            rmw(address, registers::CTRL_MEAS_REG, 0x43, 0x4F),
+        ];
+
+        let (mut sensor, mock) = configured_sensor(&Config::indoor_navigation(), expectations);
+        sensor.disable_humidity().unwrap();
+        sensor.disable_pressure().unwrap();
+        assert_eq!(sensor.config.humidity_oversampling, Oversampling::Skipped);
+        assert_eq!(sensor.config.pressure_oversampling, Oversampling::Skipped);
+
+        sensor.enable_humidity(Oversampling::Ox16).unwrap();
+        sensor.enable_pressure(Oversampling::Ox4).unwrap();
+        assert_eq!(sensor.config, Config {
+            humidity_oversampling: Oversampling::Ox16,
+            pressure_oversampling: Oversampling::Ox4,
+            ..Config::indoor_navigation()
+        });
+
+        finish(sensor, mock);
+
+        // No humidity channel to toggle on a BMP280
+        let mut expectations = get_mock_calibration_bmp280(address);
+        expectations.extend(drop_sleep(address));
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
+        let mut sensor = AtmosphericSensor::with_variant(i2c, Address::Default, Variant::Bmp280).unwrap();
+        assert_eq!(sensor.disable_humidity(), Err(SensorError::NoHumidity));
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn measure_checked() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        // Temperature frozen while pressure and humidity move
+        for step in 0..4u8 {
+            expectations.extend(rmw(address, registers::CTRL_MEAS_REG, 0x24, 0x25));
+            expectations.extend(register_read(
+                address,
+                vec![registers::PRESSURE_MSB_REG],
+                vec![82, 79 + step, 0, 128, 189, 0, 110, 213 + step],
+            ));
+        }
+        let (mut sensor, mock) = configured_sensor(&Config { mode: Mode::Sleep, ..Config::weather_monitoring() }, expectations);
+        let mut delay = NoopDelay::new();
+        // Not watched until a limit is set
+        assert!(sensor.measure_checked(&mut delay).is_ok());
+        sensor.set_stale_data_limit(Some(1));
+        assert!(sensor.measure_checked(&mut delay).is_ok());
+        assert!(sensor.measure_checked(&mut delay).is_ok());
+        assert_eq!(sensor.measure_checked(&mut delay).unwrap_err(), SensorError::Stuck);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn detect_bmp280() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = transactions![
+            register_read(address, vec![registers::CHIP_ID_REG], vec![CHIP_ID_BMP280]),
+        ];
+        // No humidity calibration
+        expectations.extend(get_mock_calibration_bmp280(address));
+        // No ctrl_hum write either
+        expectations.extend([
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, 0x00]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, 0x27]),
+        ]);
+        expectations.extend(
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![82, 79, 0, 128, 189, 0, 0x80, 0x00]),
         );
-        
+        expectations.extend(drop_sleep(address));
+
         let i2c = I2cMock::new(&expectations);
-        let mut i2c_clone = i2c.clone();
+        let mock = i2c.clone();
 
-        let mut sensor = AtmosphericSensor::new(i2c, Address::Default);
-        sensor.t_fine = 0;
-        let temperature = sensor.get_temperature_celsius().unwrap();
+        let mut sensor = AtmosphericSensor::try_new(i2c, Address::Default).unwrap();
+        assert_eq!(sensor.variant(), Variant::Bmp280);
+        sensor.start().unwrap();
+        assert_eq!(sensor.config.humidity_oversampling, Oversampling::Skipped);
+        assert_eq!(sensor.get_humidity_relative().unwrap_err(), SensorError::NoHumidity);
+        let sample = RawMeasurement::from(sensor.read_raw_bytes().unwrap());
+        assert!(sensor.compensate(&sample).unwrap().humidity_percent.is_nan());
 
-        assert!(temperature > -100.);
-        assert!(temperature < 100.);
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn reset_and_wait() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        expectations.extend(transactions![
+            I2cTransaction::write(address, vec![registers::RST_REG, 0xB6]),
+            register_read(address, vec![registers::STAT_REG], vec![0x01]),
+            register_read(address, vec![registers::STAT_REG], vec![0x00]),
+        ]);
+        // Calibration read again after the copy
+        expectations.extend(get_mock_calibration(address));
+        // A copy that never ends
+        expectations.push(I2cTransaction::write(address, vec![registers::RST_REG, 0xB6]));
+        expectations.extend((0..10).flat_map(|_| register_read(address, vec![registers::STAT_REG], vec![0x01])));
+        let (mut sensor, mock) = mock_sensor(expectations);
+        sensor.t_fine = 116770;
+        sensor.reset_and_wait(&mut NoopDelay).unwrap();
+        assert!(!sensor.configured);
+        assert_eq!(sensor.t_fine, 0);
+        assert_eq!(sensor.reset_and_wait(&mut NoopDelay), Err(SensorError::Timeout));
 
-        i2c_clone.done();
+        finish(sensor, mock);
     }
 
     #[test]
-    fn read_pressure() {
+    fn dump_registers() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::CHIP_ID_REG], vec![0x60]),
+            register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01, 0x08, 0x27, 0xA0]),
+            register_read(address, vec![registers::PRESSURE_MSB_REG], vec![1, 2, 3, 4, 5, 6, 7, 8]),
+        ];
+        let (mut sensor, mock) = mock_sensor(expectations);
+        let dump = sensor.dump_registers().unwrap();
+        assert_eq!(dump.0, [0x60, 0x01, 0x08, 0x27, 0xA0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(dump.get(registers::CTRL_MEAS_REG), Some(0x27));
+        assert_eq!(dump.get(registers::HUMIDITY_LSB_REG), Some(8));
+        assert_eq!(dump.get(registers::RST_REG), None);
+        let debug = format!("{:?}", dump);
+        assert!(debug.starts_with("{0xd0: 0x60, 0xf2: 0x01, 0xf3: 0x08"));
+        assert!(debug.ends_with("0xfe: 0x08}"));
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn status_is_one_read() {
+        let address: u8 = Address::Default.into();
+        let expectations = register_read(address, vec![registers::STAT_REG], vec![0x09]);
+        let (mut sensor, mock) = mock_sensor(expectations);
+        assert_eq!(sensor.status().unwrap(), Status { measuring: true, im_update: true });
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn configuration_getters() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = Vec::new();
+        // Indoor navigation: t 2x, p 16x, normal / h 1x / standby 0.5ms, filter 16
+        expectations.extend(transactions![
+            register_read(address, vec![registers::CTRL_MEAS_REG], vec![0x57]),
+            register_read(address, vec![registers::CTRL_MEAS_REG], vec![0x57]),
+            register_read(address, vec![registers::CTRL_MEAS_REG], vec![0x57]),
+            register_read(address, vec![registers::CTRL_HUMIDITY_REG], vec![0x01]),
+            register_read(address, vec![registers::CONFIG_REG], vec![0x10]),
+            register_read(address, vec![registers::CONFIG_REG], vec![0x10]),
+        ]);
+        let (mut sensor, mock) = mock_sensor(expectations);
+        let expected = Config::indoor_navigation();
+        assert_eq!(sensor.get_mode().unwrap(), expected.mode);
+        assert_eq!(sensor.get_temperature_oversample().unwrap(), expected.temperature_oversampling);
+        assert_eq!(sensor.get_pressure_oversample().unwrap(), expected.pressure_oversampling);
+        assert_eq!(sensor.get_humidity_oversample().unwrap(), expected.humidity_oversampling);
+        assert_eq!(sensor.get_filter().unwrap(), expected.filter);
+        assert_eq!(sensor.get_standby_time().unwrap(), expected.standby_time);
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn custom_address() {
+        let mut expectations = transactions![
+            register_read(0x42, vec![registers::CHIP_ID_REG], vec![0x60]),
+        ];
+        expectations.extend(get_mock_calibration(0x42));
+        expectations.extend(drop_sleep(0x42));
+
+        let i2c = I2cMock::new(&expectations);
+        let mut mock = i2c.clone();
+
+        assert!(AtmosphericSensor::try_new(i2c, Address::Custom(0x42)).is_ok());
+        mock.done();
+
+        // An 8-bit address is refused before touching the bus
+        let i2c = I2cMock::new(&[]);
+        let mut mock = i2c.clone();
+        assert_eq!(AtmosphericSensor::new(i2c, Address::Custom(0xEC)).err(), Some(SensorError::InvalidAddress(0xEC)));
+        mock.done();
+    }
+
+    #[test]
+    fn detect_unknown_chip() {
+        let address: u8 = Address::Default.into();
+        let expectations = transactions![
+            register_read(address, vec![registers::CHIP_ID_REG], vec![0x61]),
+        ];
+
+        let i2c = I2cMock::new(&expectations);
+        let mut mock = i2c.clone();
+
+        assert_eq!(AtmosphericSensor::try_new(i2c, Address::Default).err(), Some(SensorError::InvalidChipId(0x61)));
+
+        mock.done();
+    }
+
+    #[test]
+    fn detect_unknown_chip_over_spi() {
+        use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+        let expectations = [
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![registers::CHIP_ID_REG | 0x80]),
+            SpiTransaction::read_vec(vec![0x61]),
+            SpiTransaction::transaction_end(),
+        ];
+
+        let spi = SpiMock::new(&expectations);
+        let mut spi_clone = spi.clone();
+
+        assert_eq!(AtmosphericSensor::try_new_spi(spi).err(), Some(SensorError::InvalidChipId(0x61)));
+
+        spi_clone.done();
+    }
+
+    #[test]
+    #[cfg(feature = "sleep-on-drop")]
+    fn sleep_on_drop() {
         let address: u8 = Address::Default.into();
         let mut expectations = get_mock_calibration(address);
-        expectations.push(
-            I2cTransaction::write_read(address, vec![registers::PRESSURE_MSB_REG], vec![0])
-        );
-        expectations.push(
-            I2cTransaction::write_read(address, vec![registers::PRESSURE_LSB_REG], vec![0])
-        );
-        expectations.push(
-            I2cTransaction::write_read(address, vec![registers::PRESSURE_XLSB_REG], vec![0])
+        expectations.extend(rmw(address, registers::CTRL_MEAS_REG, 0x27, 0x24));
+        // A bus error while dropping is swallowed
+        expectations.extend(get_mock_calibration_bmp280(address));
+        expectations.extend(
+            failed_read(address, vec![registers::CTRL_MEAS_REG], vec![0x27], ErrorKind::Other),
         );
-        
+
         let i2c = I2cMock::new(&expectations);
-        let mut i2c_clone = i2c.clone();
+        let mut mock = i2c.clone();
 
-        let mut sensor = AtmosphericSensor::new(i2c, Address::Default);
-        sensor.t_fine = 0;
-        let pressure = sensor.get_pressure_pascal().unwrap();
+        drop(AtmosphericSensor::new(i2c.clone(), Address::Default).unwrap());
+        drop(AtmosphericSensor::with_variant(i2c, Address::Default, Variant::Bmp280).unwrap());
 
-        assert!(pressure > 0.0);
+        mock.done();
+    }
+
+    #[test]
+    fn release_and_bus_mut() {
+        let address: u8 = Address::Default.into();
+        let mut expectations = get_mock_calibration(address);
+        // Another device on the same bus
+        expectations.push(I2cTransaction::write(0x40, vec![0x01, 0x02]));
+        expectations.extend(drop_sleep(address));
+        expectations.push(I2cTransaction::write(0x40, vec![0x03, 0x04]));
+
+        let i2c = I2cMock::new(&expectations);
+
+        let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+        sensor.bus_mut().write(0x40, &[0x01, 0x02]).unwrap();
 
-        i2c_clone.done();
+        let mut i2c = sensor.release();
+        i2c.write(0x40, &[0x03, 0x04]).unwrap();
+
+        i2c.done();
     }
 
-    fn get_mock_calibration(address: u8) -> Vec<I2cTransaction> {
-        let expectations = vec![
-            I2cTransaction::write_read(address, vec![registers::DIG_T1_LSB_REG], ((28485_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![registers::DIG_T1_MSB_REG], ((28485_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+    #[test]
+    fn sensor_is_send_and_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<AtmosphericSensor<AtmosphericSensorI2c<I2cMock>>>();
+        assert_sync::<AtmosphericSensor<AtmosphericSensorI2c<I2cMock>>>();
+    }
+
+    #[test]
+    fn read_while_asleep() {
+        // Configured, but left asleep, so no data register is read
+        let (mut sensor, mock) = configured_sensor(&Config::default(), Vec::new());
+        assert_eq!(sensor.get_temperature_celsius().unwrap_err(), SensorError::Asleep);
+        assert!(sensor.get_pressure_pascal().is_err());
+        assert!(sensor.get_humidity_relative().is_err());
+
+        finish(sensor, mock);
+    }
+
+    #[test]
+    fn read_before_configuration() {
+        let address: u8 = Address::Default.into();
+        let normal = Config::default().mode(Mode::Normal);
+        let expectations = transactions![
+            config_writes(&normal),
+            I2cTransaction::write(address, vec![registers::RST_REG, 0xB6]),
+        ];
+
+        // Nothing written yet, the power-on oversampling skips every channel
+        let (mut sensor, mock) = mock_sensor(expectations);
+        assert_eq!(sensor.get_temperature_celsius().unwrap_err(), SensorError::NotConfigured);
+        assert_eq!(sensor.measure().unwrap_err(), SensorError::NotConfigured);
+        assert_eq!(sensor.read_raw_bytes().unwrap_err(), SensorError::NotConfigured);
+        assert_eq!(sensor.measure_forced(&mut NoopDelay::new()).unwrap_err(), SensorError::NotConfigured);
+
+        // A reset forgets the configuration again
+        sensor.apply_config(&normal).unwrap();
+        sensor.reset().unwrap();
+        assert_eq!(sensor.get_pressure_pascal().unwrap_err(), SensorError::NotConfigured);
+
+        finish(sensor, mock);
+    }
+
+    /// Delay that only records how long it was asked to wait.
+    #[derive(Default)]
+    struct RecordingDelay {
+        total_ns: u64,
+    }
+
+    impl DelayNs for RecordingDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.total_ns += u64::from(ns);
+        }
+    }
+
+    /// Sensor on a mock that serves the calibration, then `steps`, then the sleep on drop.
+    ///
+    /// Hand the sensor, or whatever ends up owning it, back to `finish` with the mock.
+    pub(crate) fn mock_sensor(steps: Vec<I2cTransaction>) -> (AtmosphericSensor<AtmosphericSensorI2c<I2cMock>>, I2cMock) {
+        let address: u8 = Address::Default.into();
+        let mut expectations = get_mock_calibration(address);
+        expectations.extend(steps);
+        expectations.extend(drop_sleep(address));
+
+        let i2c = I2cMock::new(&expectations);
+        let mock = i2c.clone();
+        (AtmosphericSensor::new(i2c, Address::Default).unwrap(), mock)
+    }
+
+    /// Like `mock_sensor`, with `config` written by `apply_config` before `steps`.
+    pub(crate) fn configured_sensor(config: &Config, steps: Vec<I2cTransaction>) -> (AtmosphericSensor<AtmosphericSensorI2c<I2cMock>>, I2cMock) {
+        let mut expectations = config_writes(config);
+        expectations.extend(steps);
+
+        let (mut sensor, mock) = mock_sensor(expectations);
+        sensor.apply_config(config).unwrap();
+        (sensor, mock)
+    }
+
+    /// Writes of `apply_config` on a BME280 that isn't running in normal mode.
+    pub(crate) fn config_writes(config: &Config) -> Vec<I2cTransaction> {
+        let address: u8 = Address::Default.into();
+        let (ctrl_hum, ctrl_meas, config_reg) = config.to_registers();
+        vec![
+            I2cTransaction::write(address, vec![registers::CTRL_HUMIDITY_REG, ctrl_hum]),
+            I2cTransaction::write(address, vec![registers::CONFIG_REG, config_reg]),
+            I2cTransaction::write(address, vec![registers::CTRL_MEAS_REG, ctrl_meas]),
+        ]
+    }
+
+    /// Drop the sensor and check the mock went through every expected transaction.
+    pub(crate) fn finish<T>(sensor: T, mut mock: I2cMock) {
+        drop(sensor);
+        mock.done();
+    }
+
+    /// Sleep write a sensor issues when dropped with `sleep-on-drop`, nothing otherwise.
+    pub(crate) fn drop_sleep(address: u8) -> Vec<I2cTransaction> {
+        if cfg!(feature = "sleep-on-drop") {
+            rmw(address, registers::CTRL_MEAS_REG, 0x00, 0x00)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Read-modify-write of a single register.
+    fn rmw(address: u8, register: u8, read: u8, written: u8) -> Vec<I2cTransaction> {
+        transactions![
+            register_read(address, vec![register], vec![read]),
+            I2cTransaction::write(address, vec![register, written]),
+        ]
+    }
+
+    /// Temperature and pressure calibration reads, all a BMP280 has.
+    fn get_mock_calibration_bmp280(address: u8) -> Vec<I2cTransaction> {
+        transactions![
+            register_read(address, vec![registers::DIG_T1_LSB_REG], ((28485_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_T1_MSB_REG], ((28485_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // T2 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_T2_LSB_REG], ((26735_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![registers::DIG_T2_MSB_REG], ((26735_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_T2_LSB_REG], ((26735_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_T2_MSB_REG], ((26735_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // T3 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_T3_LSB_REG], ((50_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![registers::DIG_T3_MSB_REG], ((50_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_T3_LSB_REG], ((50_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_T3_MSB_REG], ((50_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
 
             // Pressure calibration
             // P1 calibration
-            I2cTransaction::write_read(address, vec![0x8E], ((36738_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x8F], ((36738_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x8E], ((36738_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x8F], ((36738_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P2 calibration
-            I2cTransaction::write_read(address, vec![0x90], ((-10635_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x91], ((-10635_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x90], ((-10635_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x91], ((-10635_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P3 calibration
-            I2cTransaction::write_read(address, vec![0x92], ((3024_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x93], ((3024_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x92], ((3024_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x93], ((3024_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P4 calibration
-            I2cTransaction::write_read(address, vec![0x94], ((6980_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x95], ((6980_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x94], ((6980_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x95], ((6980_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P5 calibration
-            I2cTransaction::write_read(address, vec![0x96], ((-4_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x97], ((-4_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x96], ((-4_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x97], ((-4_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P6 calibration
-            I2cTransaction::write_read(address, vec![0x98], ((-7_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x99], ((-7_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x98], ((-7_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x99], ((-7_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P7 calibration
-            I2cTransaction::write_read(address, vec![0x9A], ((9900_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x9B], ((9900_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x9A], ((9900_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x9B], ((9900_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P8 calibration
-            I2cTransaction::write_read(address, vec![0x9C], ((-10230_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x9D], ((-10230_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x9C], ((-10230_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x9D], ((-10230_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
             // P9 calibration
-            I2cTransaction::write_read(address, vec![0x9E], ((4285_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![0x9F], ((4285_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x9E], ((4285_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![0x9F], ((4285_i64 & 0xFF00 >> 8) as u8).to_be_bytes().to_vec()),
+        ]
+    }
 
+    pub(crate) fn get_mock_calibration(address: u8) -> Vec<I2cTransaction> {
+        let mut expectations = get_mock_calibration_bmp280(address);
+        expectations.extend(transactions![
             // TODO check all calibration values from python for sample case
             // Humidity calibration
             // H1 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_H1_REG], ((75_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H1_REG], ((75_i64 & 0xFF) as u8).to_be_bytes().to_vec()),
             // H2 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_H2_LSB_REG], ((109 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![registers::DIG_H2_MSB_REG], ((1 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H2_LSB_REG], ((109 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H2_MSB_REG], ((1 & 0xFF) as u8).to_be_bytes().to_vec()),
             // H3 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_H3_REG], ((0 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H3_REG], 0_u8.to_be_bytes().to_vec()),
             // H4 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_H4_MSB_REG], ((19 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![registers::DIG_H4_LSB_REG], ((40 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H4_MSB_REG], ((19 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H4_LSB_REG], ((40 & 0xFF) as u8).to_be_bytes().to_vec()),
             // H5 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_H5_MSB_REG], ((3 & 0xFF) as u8).to_be_bytes().to_vec()),
-            I2cTransaction::write_read(address, vec![registers::DIG_H4_LSB_REG], ((40 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H5_MSB_REG], ((3 & 0xFF) as u8).to_be_bytes().to_vec()),
+            register_read(address, vec![registers::DIG_H5_LSB_REG], ((40 & 0xFF) as u8).to_be_bytes().to_vec()),
             // H6 calibration
-            I2cTransaction::write_read(address, vec![registers::DIG_H6_REG], ((30 & 0xFF) as u8).to_be_bytes().to_vec()),
-        ];
-        return expectations
+            register_read(address, vec![registers::DIG_H6_REG], ((30 & 0xFF) as u8).to_be_bytes().to_vec()),
+        ]);
+        expectations
     }
 }
\ No newline at end of file
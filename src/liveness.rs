@@ -0,0 +1,139 @@
+//! Detection of a sensor that stopped converting.
+
+// Local imports
+use crate::measurement::{is_skipped_raw, Channel};
+use crate::RawMeasurement;
+
+
+/// Tracks consecutive raw samples to flag a sensor whose data registers stopped changing.
+///
+/// The ADC noise floor makes two conversions bit-for-bit identical on every channel unlikely,
+/// so a long run of them means the sensor latched up. Heavy oversampling and filtering lower
+/// the noise, so pick a larger limit for those configurations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivenessWindow {
+    limit: usize,
+    last: Option<RawMeasurement>,
+    repeats: usize,
+    /// Repeats of temperature, pressure and humidity on their own, when tracked per channel.
+    channel_repeats: Option<[usize; 3]>,
+}
+
+impl LivenessWindow {
+    /// Flag the sensor once more than `limit` consecutive samples repeat the previous one.
+    pub fn new(limit: usize) -> LivenessWindow {
+        LivenessWindow { limit, last: None, repeats: 0, channel_repeats: None }
+    }
+
+    /// Flag the sensor once any single channel repeats its previous raw value more than
+    /// `limit` times in a row, e.g. a temperature register frozen after a brown-out while
+    /// pressure still moves.
+    ///
+    /// Skipped channels always hold their reset value and are left out. A single channel
+    /// repeats far more often than a whole sample, humidity most, so pick a larger limit than
+    /// for `new`.
+    pub fn per_channel(limit: usize) -> LivenessWindow {
+        LivenessWindow { channel_repeats: Some([0; 3]), ..LivenessWindow::new(limit) }
+    }
+
+    /// Record a sample and return whether the sensor looks stuck.
+    pub fn update(&mut self, sample: &RawMeasurement) -> bool {
+        if let (Some(channel_repeats), Some(last)) = (&mut self.channel_repeats, self.last) {
+            let channels = [
+                (Channel::Temperature, sample.temperature, last.temperature),
+                (Channel::Pressure, sample.pressure, last.pressure),
+                (Channel::Humidity, sample.humidity, last.humidity),
+            ];
+            for (repeats, (channel, raw, last_raw)) in channel_repeats.iter_mut().zip(channels) {
+                *repeats = match raw == last_raw && !is_skipped_raw(channel, raw) {
+                    true => *repeats + 1,
+                    false => 0,
+                };
+            }
+        }
+        if self.last == Some(*sample) {
+            self.repeats += 1;
+        } else {
+            self.last = Some(*sample);
+            self.repeats = 0;
+        }
+        self.is_stuck()
+    }
+
+    /// Whether more than `limit` consecutive samples were identical.
+    pub fn is_stuck(&self) -> bool {
+        self.repeats() > self.limit
+    }
+
+    /// Number of consecutive samples identical to the one before them, or per channel the
+    /// longest run of any channel.
+    pub fn repeats(&self) -> usize {
+        match self.channel_repeats {
+            Some(channel_repeats) => channel_repeats.into_iter().max().unwrap_or(0),
+            None => self.repeats,
+        }
+    }
+
+    /// Forget the tracked samples, e.g. after resetting the sensor.
+    pub fn clear(&mut self) {
+        self.last = None;
+        self.repeats = 0;
+        if let Some(channel_repeats) = &mut self.channel_repeats {
+            *channel_repeats = [0; 3];
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: RawMeasurement = RawMeasurement { pressure: 336880, temperature: 527312, humidity: 28373 };
+
+    #[test]
+    fn flags_repeated_samples() {
+        let mut window = LivenessWindow::new(2);
+        assert!(!window.update(&SAMPLE));
+        assert!(!window.update(&SAMPLE));
+        assert!(!window.update(&SAMPLE));
+        assert!(window.update(&SAMPLE));
+        assert_eq!(window.repeats(), 3);
+    }
+
+    #[test]
+    fn any_change_restarts_the_count() {
+        let mut window = LivenessWindow::new(1);
+        window.update(&SAMPLE);
+        window.update(&SAMPLE);
+        assert!(!window.update(&RawMeasurement { humidity: SAMPLE.humidity + 1, ..SAMPLE }));
+        assert_eq!(window.repeats(), 0);
+
+        window.update(&SAMPLE);
+        window.update(&SAMPLE);
+        assert!(window.update(&SAMPLE));
+        window.clear();
+        assert!(!window.is_stuck());
+    }
+
+    #[test]
+    fn one_frozen_channel() {
+        let mut window = LivenessWindow::per_channel(1);
+        let mut whole = LivenessWindow::new(1);
+        for step in 0..3 {
+            // Temperature frozen, pressure still moving, humidity skipped
+            let sample = RawMeasurement { pressure: SAMPLE.pressure + step, humidity: 0x8000, ..SAMPLE };
+            let stuck = window.update(&sample);
+            assert!(!whole.update(&sample));
+            assert_eq!(stuck, step == 2);
+        }
+        assert_eq!(window.repeats(), 2);
+
+        window.clear();
+        assert_eq!(window.repeats(), 0);
+        // The skipped channel never counts
+        for step in 0..3 {
+            assert!(!window.update(&RawMeasurement { pressure: step, temperature: step, humidity: 0x8000 }));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Measurement types returned by the sensor.
+
+/// Length in bytes of the data registers, from pressure MSB to humidity LSB.
+pub const DATA_FRAME_LENGTH: usize = 8;
+
+
+/// Raw temperature read back when the channel is skipped or wasn't sampled since reset.
+pub const SKIPPED_TEMPERATURE_RAW: u32 = 0x80000;
+
+/// Raw pressure read back when the channel is skipped or wasn't sampled since reset.
+pub const SKIPPED_PRESSURE_RAW: u32 = 0x80000;
+
+/// Raw humidity read back when the channel is skipped or wasn't sampled since reset.
+pub const SKIPPED_HUMIDITY_RAW: u32 = 0x8000;
+
+
+/// Measurement channels of the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channel {
+    Temperature,
+    Pressure,
+    Humidity
+}
+
+/// Whether a raw value is the reset value of its channel, i.e. holds no data.
+pub fn is_skipped_raw(channel: Channel, raw: u32) -> bool {
+    match channel {
+        Channel::Temperature => raw == SKIPPED_TEMPERATURE_RAW,
+        Channel::Pressure => raw == SKIPPED_PRESSURE_RAW,
+        Channel::Humidity => raw == SKIPPED_HUMIDITY_RAW,
+    }
+}
+
+
+/// Errors linked to decoding raw measurements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawMeasurementError {
+    /// The frame does not hold exactly `DATA_FRAME_LENGTH` bytes.
+    InvalidLength(usize)
+}
+
+
+/// Uncompensated ADC values of one sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawMeasurement {
+    pub pressure: u32,
+    pub temperature: u32,
+    pub humidity: u32,
+}
+
+impl From<[u8; DATA_FRAME_LENGTH]> for RawMeasurement {
+    /// Decode the data registers as laid out from 0xF7 to 0xFE.
+    fn from(data: [u8; DATA_FRAME_LENGTH]) -> Self {
+        RawMeasurement {
+            pressure: (u32::from(data[0]) << 12) | (u32::from(data[1]) << 4) | (u32::from(data[2]) >> 4),
+            temperature: (u32::from(data[3]) << 12) | (u32::from(data[4]) << 4) | (u32::from(data[5]) >> 4),
+            humidity: (u32::from(data[6]) << 8) | u32::from(data[7]),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for RawMeasurement {
+    type Error = RawMeasurementError;
+
+    /// Decode a captured frame of the data registers, checking its length.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let frame: [u8; DATA_FRAME_LENGTH] = data.try_into()
+            .map_err(|_| RawMeasurementError::InvalidLength(data.len()))?;
+        Ok(RawMeasurement::from(frame))
+    }
+}
+
+
+/// Temperature, pressure and humidity taken from the same sample.
+///
+/// Pressure and humidity are NaN when their channel was skipped, and humidity is NaN on
+/// variants without the humidity channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Measurements {
+    pub temperature_celsius: f64,
+    pub pressure_pascal: f64,
+    pub humidity_percent: f64,
+}
+
+
+/// Weather snapshot with every quantity derived from the same sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weather {
+    pub temperature_celsius: f64,
+    pub pressure_pascal: f64,
+    pub humidity_percent: f64,
+    pub altitude_meters: f64,
+    pub dew_point_celsius: f64,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_frame() {
+        let frame: &[u8] = &[82, 79, 0, 128, 189, 0, 110, 213];
+        let raw = RawMeasurement::try_from(frame).unwrap();
+        assert_eq!(raw.pressure, 0x524F0);
+        assert_eq!(raw.temperature, 0x80BD0);
+        assert_eq!(raw.humidity, 0x6ED5);
+    }
+
+    #[test]
+    fn detect_skipped_channels() {
+        // Power-on contents of the data registers
+        let raw = RawMeasurement::from([0x80, 0x00, 0x00, 0x80, 0x00, 0x00, 0x80, 0x00]);
+        assert!(is_skipped_raw(Channel::Pressure, raw.pressure));
+        assert!(is_skipped_raw(Channel::Temperature, raw.temperature));
+        assert!(is_skipped_raw(Channel::Humidity, raw.humidity));
+        assert!(!is_skipped_raw(Channel::Humidity, raw.pressure));
+    }
+
+    #[test]
+    fn reject_wrong_length() {
+        let short: &[u8] = &[0; 7];
+        let long: &[u8] = &[0; 9];
+        assert_eq!(RawMeasurement::try_from(short), Err(RawMeasurementError::InvalidLength(7)));
+        assert_eq!(RawMeasurement::try_from(long), Err(RawMeasurementError::InvalidLength(9)));
+    }
+}
@@ -0,0 +1,16 @@
+//! Operating ranges of the sensor, from the datasheet.
+//!
+//! Readings outside these spans are not specified by Bosch and should be treated as suspect.
+
+// Public imports
+use core::ops::RangeInclusive;
+
+
+/// Operating temperature range in celsius.
+pub const TEMPERATURE_CELSIUS: RangeInclusive<f64> = -40.0..=85.0;
+
+/// Operating pressure range in pascal.
+pub const PRESSURE_PASCAL: RangeInclusive<f64> = 30000.0..=110000.0;
+
+/// Relative humidity range in percent.
+pub const HUMIDITY_PERCENT: RangeInclusive<f64> = 0.0..=100.0;
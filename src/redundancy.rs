@@ -0,0 +1,111 @@
+//! Cross-checking of redundant sensors.
+
+// Local imports
+use crate::{AtmosphericSensor, Bus, Measurements, SensorError};
+
+
+/// Largest disagreement accepted between two sensors, per channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub temperature_celsius: f64,
+    pub pressure_pascal: f64,
+    pub humidity_percent: f64,
+}
+
+
+/// Outcome of comparing two sensors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossCheck {
+    /// Reading of the first sensor minus reading of the second, per channel.
+    pub delta: Measurements,
+    /// At least one channel differs by more than its tolerance, or is NaN on one sensor only.
+    pub disagree: bool,
+}
+
+impl CrossCheck {
+    /// Compare two samples against a tolerance.
+    pub fn compare(first: &Measurements, second: &Measurements, tolerance: &Tolerance) -> CrossCheck {
+        let delta = Measurements {
+            temperature_celsius: first.temperature_celsius - second.temperature_celsius,
+            pressure_pascal: first.pressure_pascal - second.pressure_pascal,
+            humidity_percent: first.humidity_percent - second.humidity_percent,
+        };
+        let disagree = channel_disagrees(first.temperature_celsius, second.temperature_celsius, tolerance.temperature_celsius)
+            || channel_disagrees(first.pressure_pascal, second.pressure_pascal, tolerance.pressure_pascal)
+            || channel_disagrees(first.humidity_percent, second.humidity_percent, tolerance.humidity_percent);
+
+        CrossCheck { delta, disagree }
+    }
+}
+
+/// Whether two readings of a channel differ by more than `tolerance`.
+///
+/// NaN marks a channel a sensor skips or lacks. Both sides NaN agree, one side only doesn't.
+fn channel_disagrees(first: f64, second: f64, tolerance: f64) -> bool {
+    match (first.is_nan(), second.is_nan()) {
+        (true, true) => false,
+        (false, false) => (first - second).abs() > tolerance,
+        _ => true,
+    }
+}
+
+
+/// Read both sensors back to back and compare their samples.
+///
+/// Each sensor is read with `measure`, so every channel of one sensor comes from the same
+/// sample and the two samples are as close in time as the bus allows. Both buses must report
+/// the same error type, as two devices on one shared bus do.
+pub fn cross_check<E, A: Bus<Error = E>, B: Bus<Error = E>>(
+    first: &mut AtmosphericSensor<A>,
+    second: &mut AtmosphericSensor<B>,
+    tolerance: &Tolerance,
+) -> Result<CrossCheck, SensorError<E>> {
+    let first_sample = first.measure()?;
+    let second_sample = second.measure()?;
+    Ok(CrossCheck::compare(&first_sample, &second_sample, tolerance))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: Tolerance = Tolerance {
+        temperature_celsius: 0.5,
+        pressure_pascal: 100.0,
+        humidity_percent: 3.0,
+    };
+
+    fn sample(temperature_celsius: f64, pressure_pascal: f64, humidity_percent: f64) -> Measurements {
+        Measurements { temperature_celsius, pressure_pascal, humidity_percent }
+    }
+
+    #[test]
+    fn agreeing_sensors() {
+        let check = CrossCheck::compare(&sample(21.0, 101300.0, 40.0), &sample(20.8, 101250.0, 42.0), &TOLERANCE);
+        assert!(!check.disagree);
+        assert!((check.delta.temperature_celsius - 0.2).abs() < 1e-9);
+        assert!((check.delta.pressure_pascal - 50.0).abs() < 1e-9);
+        assert!((check.delta.humidity_percent + 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn single_channel_disagreement() {
+        let check = CrossCheck::compare(&sample(21.0, 101300.0, 40.0), &sample(21.0, 101300.0, 48.0), &TOLERANCE);
+        assert!(check.disagree);
+    }
+
+    #[test]
+    fn nan_readings() {
+        // Humidity missing on one sensor only
+        let check = CrossCheck::compare(&sample(21.0, 101300.0, 40.0), &sample(21.0, 101300.0, f64::NAN), &TOLERANCE);
+        assert!(check.disagree);
+        assert!(check.delta.humidity_percent.is_nan());
+        let check = CrossCheck::compare(&sample(21.0, f64::NAN, 40.0), &sample(21.0, 101300.0, 40.0), &TOLERANCE);
+        assert!(check.disagree);
+
+        // Two BMP280s, neither has humidity
+        let check = CrossCheck::compare(&sample(21.0, 101300.0, f64::NAN), &sample(21.0, 101300.0, f64::NAN), &TOLERANCE);
+        assert!(!check.disagree);
+    }
+}
@@ -0,0 +1,125 @@
+//! SPI transport.
+//!
+//! In SPI mode the sensor uses bit 7 of the register address to pick the direction, set for a
+//! read and clear for a write, and drops it from the address.
+
+// Public imports
+use embedded_hal::spi::{Operation, SpiDevice};
+
+// Local imports
+use crate::bus::Bus;
+
+
+/// Read/write bit of the register address.
+const READ_BIT: u8 = 0x80;
+
+
+/// A wrapper for the SPI device to represent the sensor
+pub struct AtmosphericSensorSpi<SPI> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice> AtmosphericSensorSpi<SPI> {
+    /// Create new AtmosphericSensorSpi, the device takes care of chip select.
+    pub fn new(spi: SPI) -> AtmosphericSensorSpi<SPI> {
+        AtmosphericSensorSpi { spi }
+    }
+
+    /// Give the SPI device back.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
+    /// Underlying SPI device.
+    pub fn bus_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+}
+
+impl<SPI: SpiDevice> Bus for AtmosphericSensorSpi<SPI> {
+    type Error = SPI::Error;
+
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), SPI::Error> {
+        // Chip select stays low across both operations, so the read auto-increments
+        self.spi.transaction(&mut [
+            Operation::Write(&[register | READ_BIT]),
+            Operation::Read(buffer),
+        ])
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), SPI::Error> {
+        self.spi.write(&[register & !READ_BIT, value])
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+    use super::AtmosphericSensorSpi;
+    use crate::i2c::constants::{registers, values};
+    use crate::i2c::Registers;
+
+    #[test]
+    fn read_sets_and_write_clears_the_msb() {
+        let expectations = [
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![registers::CHIP_ID_REG | 0x80]),
+            SpiTransaction::read_vec(vec![values::CHIP_ID_BME280]),
+            SpiTransaction::transaction_end(),
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![registers::RST_REG & 0x7F, values::SOFT_RESET]),
+            SpiTransaction::transaction_end(),
+        ];
+        let spi = SpiMock::new(&expectations);
+        let mut spi_clone = spi.clone();
+
+        let mut dev = AtmosphericSensorSpi::new(spi);
+        assert_eq!(dev.get_id().unwrap(), values::CHIP_ID_BME280);
+        dev.reset().unwrap();
+
+        spi_clone.done();
+    }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn traced() {
+        use crate::bus::{Direction, Traced};
+
+        let expectations = [
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![registers::CHIP_ID_REG | 0x80]),
+            SpiTransaction::read_vec(vec![values::CHIP_ID_BME280]),
+            SpiTransaction::transaction_end(),
+        ];
+        let spi = SpiMock::new(&expectations);
+        let mut spi_clone = spi.clone();
+
+        let mut events = Vec::new();
+        let mut dev = Traced::new(AtmosphericSensorSpi::new(spi), |event: &crate::TraceEvent| {
+            events.push((event.direction, event.register, event.bytes.to_vec()));
+        });
+        assert_eq!(dev.get_id().unwrap(), values::CHIP_ID_BME280);
+        drop(dev);
+
+        spi_clone.done();
+        assert_eq!(events, [(Direction::Read, registers::CHIP_ID_REG, vec![values::CHIP_ID_BME280])]);
+    }
+
+    #[test]
+    fn burst_is_one_transaction() {
+        let expectations = [
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![registers::PRESSURE_MSB_REG | 0x80]),
+            SpiTransaction::read_vec(vec![82, 79, 0, 128, 189, 0, 110, 213]),
+            SpiTransaction::transaction_end(),
+        ];
+        let spi = SpiMock::new(&expectations);
+        let mut spi_clone = spi.clone();
+
+        let mut dev = AtmosphericSensorSpi::new(spi);
+        assert_eq!(dev.read_burst().unwrap(), [82, 79, 0, 128, 189, 0, 110, 213]);
+
+        spi_clone.done();
+    }
+}
@@ -0,0 +1,17 @@
+//! Logical state of the sensor, for application state machines.
+
+
+/// State of the sensor as seen by the driver, see `AtmosphericSensor::state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorState {
+    /// Not configured since construction or reset, or still copying its calibration from NVM.
+    Uninitialized,
+    /// Configured in sleep mode, no conversion running.
+    Sleeping,
+    /// A conversion is running.
+    Measuring,
+    /// Idle with a sample in the data registers.
+    Ready,
+    /// The mode in the sensor doesn't match the configured one, e.g. after a brown-out reset.
+    Fault,
+}
@@ -0,0 +1,36 @@
+//! Paced iteration over measurements.
+
+// Public imports
+use embedded_hal::delay::DelayNs;
+
+// Local imports
+use crate::{AtmosphericSensor, Bus, Measurements, Mode, SensorError};
+
+
+/// Endless iterator of measurements, see `AtmosphericSensor::measurements`.
+pub struct MeasurementStream<'a, B: Bus, D> {
+    sensor: &'a mut AtmosphericSensor<B>,
+    delay: &'a mut D,
+}
+
+impl<'a, B: Bus, D: DelayNs> MeasurementStream<'a, B, D> {
+    pub(crate) fn new(sensor: &'a mut AtmosphericSensor<B>, delay: &'a mut D) -> Self {
+        MeasurementStream { sensor, delay }
+    }
+}
+
+impl<B: Bus, D: DelayNs> Iterator for MeasurementStream<'_, B, D> {
+    type Item = Result<Measurements, SensorError<B::Error>>;
+
+    /// Wait one output data period and read, or trigger a measurement in forced mode.
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = match self.sensor.config.mode {
+            Mode::Forced => self.sensor.measure_forced(self.delay),
+            _ => {
+                self.delay.delay_us(self.sensor.output_data_period_us());
+                self.sensor.measure()
+            }
+        };
+        Some(sample)
+    }
+}
@@ -0,0 +1,69 @@
+//! Conversions from readings to `uom` quantities.
+
+// Public imports
+use uom::si::f64::{Pressure, Ratio, ThermodynamicTemperature};
+use uom::si::pressure::pascal;
+use uom::si::ratio::percent;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+// Local imports
+use crate::Measurements;
+
+
+impl Measurements {
+    /// Temperature as a `uom` quantity.
+    pub fn temperature(&self) -> ThermodynamicTemperature {
+        ThermodynamicTemperature::new::<degree_celsius>(self.temperature_celsius)
+    }
+
+    /// Pressure as a `uom` quantity.
+    pub fn pressure(&self) -> Pressure {
+        Pressure::new::<pascal>(self.pressure_pascal)
+    }
+
+    /// Relative humidity as a `uom` ratio.
+    pub fn humidity(&self) -> Ratio {
+        Ratio::new::<percent>(self.humidity_percent)
+    }
+}
+
+impl From<Measurements> for ThermodynamicTemperature {
+    fn from(sample: Measurements) -> Self {
+        sample.temperature()
+    }
+}
+
+impl From<Measurements> for Pressure {
+    fn from(sample: Measurements) -> Self {
+        sample.pressure()
+    }
+}
+
+impl From<Measurements> for Ratio {
+    fn from(sample: Measurements) -> Self {
+        sample.humidity()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use uom::si::pressure::hectopascal;
+    use uom::si::ratio::ratio;
+    use uom::si::thermodynamic_temperature::kelvin;
+
+    use super::*;
+
+    #[test]
+    fn convert_sample() {
+        let sample = Measurements { temperature_celsius: 21.5, pressure_pascal: 101325.0, humidity_percent: 40.0 };
+
+        let temperature: ThermodynamicTemperature = sample.into();
+        let pressure: Pressure = sample.into();
+        let humidity: Ratio = sample.into();
+
+        assert!((temperature.get::<kelvin>() - 294.65).abs() < 1e-9);
+        assert!((pressure.get::<hectopascal>() - 1013.25).abs() < 1e-9);
+        assert!((humidity.get::<ratio>() - 0.4).abs() < 1e-12);
+    }
+}
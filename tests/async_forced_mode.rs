@@ -0,0 +1,75 @@
+#![cfg(feature = "async")]
+
+use embassy_futures::block_on;
+use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+use atmospheric_sensor::{Address, AtmosphericSensorAsync, SensorError};
+
+const ADDRESS: u8 = 0x76;
+
+const CHIP_ID_REG: u8 = 0xD0;
+const CALIBRATION_REG: u8 = 0x88;
+const HUMIDITY_CALIBRATION_REG: u8 = 0xE1;
+const CTRL_HUM_REG: u8 = 0xF2;
+const STAT_REG: u8 = 0xF3;
+const CTRL_MEAS_REG: u8 = 0xF4;
+const CONFIG_REG: u8 = 0xF5;
+const DATA_REG: u8 = 0xF7;
+
+/// Chip ID and calibration bursts issued on construction, with the sample coefficients from
+/// the datasheet reference implementation, then the configuration `start` writes.
+fn started() -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::write_read(ADDRESS, vec![CHIP_ID_REG], vec![0x60]),
+        I2cTransaction::write_read(ADDRESS, vec![CALIBRATION_REG], vec![
+            0x45, 0x6F, 0x6F, 0x68, 0x32, 0x00, 0x82, 0x8F, 0x75, 0xD6, 0xD0, 0x0B, 0x44, 0x1B,
+            0xFC, 0xFF, 0xF9, 0xFF, 0xAC, 0x26, 0x0A, 0xD8, 0xBD, 0x10, 0x00, 0x4B,
+        ]),
+        I2cTransaction::write_read(ADDRESS, vec![HUMIDITY_CALIBRATION_REG], vec![0x6D, 0x01, 0x00, 0x13, 0x28, 0x03, 0x1E]),
+        I2cTransaction::write(ADDRESS, vec![CTRL_MEAS_REG, 0x24]),
+        I2cTransaction::write(ADDRESS, vec![CONFIG_REG, 0x00]),
+        I2cTransaction::write(ADDRESS, vec![CTRL_HUM_REG, 0x01]),
+        I2cTransaction::write(ADDRESS, vec![CTRL_MEAS_REG, 0x27]),
+    ]
+}
+
+#[test]
+fn forced_measurement_awaits_between_polls() {
+    let mut expectations = started();
+    expectations.extend([
+        I2cTransaction::write(ADDRESS, vec![CTRL_MEAS_REG, 0x25]),
+        I2cTransaction::write_read(ADDRESS, vec![STAT_REG], vec![0x08]),
+        I2cTransaction::write_read(ADDRESS, vec![STAT_REG], vec![0x00]),
+        I2cTransaction::write_read(ADDRESS, vec![DATA_REG], vec![82, 79, 0, 128, 189, 0, 117, 97]),
+    ]);
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+    let mut delay = CheckedDelay::new(&[DelayTransaction::async_delay_us(1000), DelayTransaction::async_delay_us(1000)]);
+
+    block_on(async {
+        let mut sensor = AtmosphericSensorAsync::new(i2c, Address::Default).await.unwrap();
+        sensor.start().await.unwrap();
+        let sample = sensor.measure_forced(&mut delay).await.unwrap();
+        assert_eq!(sample.temperature_celsius, 22.81);
+        assert_eq!(sample.pressure_pascal, 26010243.0 / 256.0);
+    });
+
+    i2c_clone.done();
+    delay.done();
+}
+
+#[test]
+fn forced_measurement_requires_a_configuration() {
+    let i2c = I2cMock::new(&started()[..3]);
+    let mut i2c_clone = i2c.clone();
+    let mut delay = CheckedDelay::new(&[]);
+
+    block_on(async {
+        let mut sensor = AtmosphericSensorAsync::new(i2c, Address::Default).await.unwrap();
+        assert_eq!(sensor.measure_forced(&mut delay).await, Err(SensorError::NotConfigured));
+    });
+
+    i2c_clone.done();
+    delay.done();
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "async")]
+
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+use embassy_futures::{block_on, join::join};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+use atmospheric_sensor::{Address, AtmosphericSensorAsync, Config};
+
+const PRIMARY: u8 = 0x76;
+const SECONDARY: u8 = 0x77;
+
+const CHIP_ID_REG: u8 = 0xD0;
+const CALIBRATION_REG: u8 = 0x88;
+const HUMIDITY_CALIBRATION_REG: u8 = 0xE1;
+const CTRL_HUM_REG: u8 = 0xF2;
+const STAT_REG: u8 = 0xF3;
+const CTRL_MEAS_REG: u8 = 0xF4;
+const CONFIG_REG: u8 = 0xF5;
+const DATA_REG: u8 = 0xF7;
+
+/// Chip ID and calibration bursts issued on construction, with the sample coefficients from
+/// the datasheet reference implementation.
+fn construction(address: u8) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::write_read(address, vec![CHIP_ID_REG], vec![0x60]),
+        I2cTransaction::write_read(address, vec![CALIBRATION_REG], vec![
+            0x45, 0x6F, 0x6F, 0x68, 0x32, 0x00, 0x82, 0x8F, 0x75, 0xD6, 0xD0, 0x0B, 0x44, 0x1B,
+            0xFC, 0xFF, 0xF9, 0xFF, 0xAC, 0x26, 0x0A, 0xD8, 0xBD, 0x10, 0x00, 0x4B,
+        ]),
+        I2cTransaction::write_read(address, vec![HUMIDITY_CALIBRATION_REG], vec![0x6D, 0x01, 0x00, 0x13, 0x28, 0x03, 0x1E]),
+    ]
+}
+
+/// Low power preset written register by register.
+fn configuration(address: u8) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::write(address, vec![CTRL_MEAS_REG, 0x24]),
+        I2cTransaction::write(address, vec![CONFIG_REG, 0xA0]),
+        I2cTransaction::write(address, vec![CTRL_HUM_REG, 0x01]),
+        I2cTransaction::write(address, vec![CTRL_MEAS_REG, 0x27]),
+    ]
+}
+
+/// One stable burst of the data registers.
+fn measurement(address: u8) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::write_read(address, vec![STAT_REG], vec![0x00]),
+        I2cTransaction::write_read(address, vec![DATA_REG], vec![82, 79, 0, 128, 189, 0, 117, 97]),
+        I2cTransaction::write_read(address, vec![STAT_REG], vec![0x00]),
+    ]
+}
+
+#[test]
+fn two_sensors_share_one_bus() {
+    let mut expectations = Vec::new();
+    for address in [PRIMARY, SECONDARY] {
+        expectations.extend(construction(address));
+    }
+    for address in [PRIMARY, SECONDARY] {
+        expectations.extend(configuration(address));
+    }
+    for address in [PRIMARY, SECONDARY] {
+        expectations.extend(measurement(address));
+    }
+
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+    let bus: Mutex<NoopRawMutex, I2cMock> = Mutex::new(i2c);
+
+    block_on(async {
+        let mut primary = AtmosphericSensorAsync::new(I2cDevice::new(&bus), Address::Default).await.unwrap();
+        let mut secondary = AtmosphericSensorAsync::new(I2cDevice::new(&bus), Address::Alternative).await.unwrap();
+        primary.apply_config(&Config::low_power()).await.unwrap();
+        secondary.apply_config(&Config::low_power()).await.unwrap();
+
+        // Each sensor only locks the bus per transaction, so neither blocks the other
+        let (first, second) = join(primary.measure(), secondary.measure()).await;
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first, second);
+        assert_eq!(first.temperature_celsius, 22.81);
+        assert_eq!(first.pressure_pascal, 26010243.0 / 256.0);
+    });
+
+    i2c_clone.done();
+}
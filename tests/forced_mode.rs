@@ -0,0 +1,238 @@
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+
+use atmospheric_sensor::{Address, AtmosphericSensor, Config, Mode, SensorError};
+#[cfg(feature = "test-util")]
+use atmospheric_sensor::RawMeasurement;
+
+const ADDRESS: u8 = 0x76;
+
+const CHIP_ID_REG: u8 = 0xD0;
+const CTRL_HUM_REG: u8 = 0xF2;
+const CTRL_MEAS_REG: u8 = 0xF4;
+const CONFIG_REG: u8 = 0xF5;
+const STAT_REG: u8 = 0xF3;
+const DATA_REG: u8 = 0xF7;
+
+/// Register read as the mock sees it: one `write_read`, or with `split-write-read` a `write`
+/// of the register followed by a `read`.
+fn read(register: u8, response: Vec<u8>) -> Vec<I2cTransaction> {
+    if cfg!(feature = "split-write-read") {
+        vec![I2cTransaction::write(ADDRESS, vec![register]), I2cTransaction::read(ADDRESS, response)]
+    } else {
+        vec![I2cTransaction::write_read(ADDRESS, vec![register], response)]
+    }
+}
+
+/// Single register write.
+fn write(register: u8, value: u8) -> Vec<I2cTransaction> {
+    vec![I2cTransaction::write(ADDRESS, vec![register, value])]
+}
+
+/// Sleep write a sensor issues when dropped with `sleep-on-drop`, nothing otherwise.
+fn drop_sleep() -> Vec<I2cTransaction> {
+    if cfg!(feature = "sleep-on-drop") {
+        [read(CTRL_MEAS_REG, vec![0x00]), write(CTRL_MEAS_REG, 0x00)].concat()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Calibration reads issued on construction, with the sample coefficients from the datasheet
+/// reference implementation.
+fn calibration() -> Vec<I2cTransaction> {
+    let registers: [(u8, u8); 32] = [
+        // T1 = 28485, T2 = 26735, T3 = 50
+        (0x88, 0x45), (0x89, 0x6F), (0x8A, 0x6F), (0x8B, 0x68), (0x8C, 0x32), (0x8D, 0x00),
+        // P1 = 36738, P2 = -10635, P3 = 3024
+        (0x8E, 0x82), (0x8F, 0x8F), (0x90, 0x75), (0x91, 0xD6), (0x92, 0xD0), (0x93, 0x0B),
+        // P4 = 6980, P5 = -4, P6 = -7
+        (0x94, 0x44), (0x95, 0x1B), (0x96, 0xFC), (0x97, 0xFF), (0x98, 0xF9), (0x99, 0xFF),
+        // P7 = 9900, P8 = -10230, P9 = 4285
+        (0x9A, 0xAC), (0x9B, 0x26), (0x9C, 0x0A), (0x9D, 0xD8), (0x9E, 0xBD), (0x9F, 0x10),
+        // H1 = 75, H2 = 365, H3 = 0
+        (0xA1, 0x4B), (0xE1, 0x6D), (0xE2, 0x01), (0xE3, 0x00),
+        // H4 = 312, H5 = 50 share 0xE5, H6 = 30
+        (0xE4, 0x13), (0xE5, 0x28), (0xE6, 0x03), (0xE5, 0x28),
+    ];
+    let mut transactions: Vec<I2cTransaction> = registers.iter()
+        .flat_map(|(register, value)| read(*register, vec![*value]))
+        .collect();
+    transactions.extend(read(0xE7, vec![0x1E]));
+    transactions
+}
+
+#[test]
+fn forced_measurement_polls_until_idle() {
+    let mut expectations = calibration();
+    expectations.extend([
+        // Mode bits set to forced, oversampling kept
+        read(CTRL_MEAS_REG, vec![0x24]),
+        write(CTRL_MEAS_REG, 0x25),
+        // Conversion running, then done
+        read(STAT_REG, vec![0x08]),
+        read(STAT_REG, vec![0x08]),
+        read(STAT_REG, vec![0x00]),
+        // Data burst only after the sensor is idle
+        read(DATA_REG, vec![82, 79, 0, 128, 189, 0, 117, 97]),
+    ].concat());
+
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    let sample = sensor.measure_forced_polled(&mut NoopDelay::new()).unwrap();
+
+    assert_eq!(sample.temperature_celsius, 22.81);
+    assert_eq!(sample.pressure_pascal, 26010243.0 / 256.0);
+    assert_eq!(sample.humidity_percent, 57350.0 / 1024.0);
+
+    drop(sensor);
+    i2c_clone.done();
+}
+
+#[test]
+fn forced_measurement_without_a_timer() {
+    let mut expectations = calibration();
+    expectations.extend([
+        // Weather monitoring preset, left asleep
+        write(CTRL_HUM_REG, 0x01),
+        write(CONFIG_REG, 0x00),
+        write(CTRL_MEAS_REG, 0x24),
+        read(CTRL_MEAS_REG, vec![0x24]),
+        write(CTRL_MEAS_REG, 0x25),
+        // Not started yet, then running, then done
+        read(STAT_REG, vec![0x00]),
+        read(STAT_REG, vec![0x08]),
+        read(STAT_REG, vec![0x00]),
+        read(DATA_REG, vec![82, 79, 0, 128, 189, 0, 117, 97]),
+        // Second measurement never leaves the polls budget
+        read(CTRL_MEAS_REG, vec![0x24]),
+        write(CTRL_MEAS_REG, 0x25),
+        read(STAT_REG, vec![0x08]),
+        read(STAT_REG, vec![0x08]),
+    ].concat());
+
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    sensor.apply_config(&Config { mode: Mode::Sleep, ..Config::weather_monitoring() }).unwrap();
+    let sample = sensor.measure_forced_busy(10).unwrap();
+    assert_eq!(sample.temperature_celsius, 22.81);
+    assert_eq!(sensor.measure_forced_busy(2), Err(SensorError::Timeout));
+
+    drop(sensor);
+    i2c_clone.done();
+}
+
+#[test]
+fn forced_measurement_gives_up() {
+    let mut expectations = calibration();
+    expectations.extend([
+        read(CTRL_MEAS_REG, vec![0x24]),
+        write(CTRL_MEAS_REG, 0x25),
+    ].concat());
+    // Stuck measuring for all 150 polls, the data is never read
+    expectations.extend((0..150).flat_map(|_| read(STAT_REG, vec![0x08])));
+
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    assert_eq!(sensor.measure_forced_polled(&mut NoopDelay::new()), Err(SensorError::Timeout));
+
+    drop(sensor);
+    i2c_clone.done();
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn calibration_read_on_construction() {
+    let mut expectations = calibration();
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    assert_eq!(sensor.temperature_calibration(), (28485, 26735, 50));
+    assert_eq!(sensor.pressure_calibration(), (36738, [-10635, 3024, 6980, -4, -7, 9900, -10230, 4285]));
+    assert_eq!(sensor.humidity_calibration(), Some((75, 365, 0, 312, 50, 30)));
+
+    // Compensating a full sample recomputes t_fine from its temperature
+    let raw = RawMeasurement { pressure: 336880, temperature: 527312, humidity: 30049 };
+    sensor.set_t_fine(120035);
+    sensor.compensate(&raw).unwrap();
+    assert_eq!(sensor.t_fine(), 116770);
+
+    drop(sensor);
+    i2c_clone.done();
+}
+
+/// Register writes of the weather monitoring preset on a sensor fresh out of reset, followed by
+/// a forced measurement returning `data`.
+fn verification(data: [u8; 8]) -> Vec<I2cTransaction> {
+    [
+        // 1x humidity, standby and filter, then 1x temperature and pressure, left asleep
+        write(CTRL_HUM_REG, 0x01),
+        write(CONFIG_REG, 0x00),
+        write(CTRL_MEAS_REG, 0x24),
+        // Forced measurement
+        read(CTRL_MEAS_REG, vec![0x24]),
+        write(CTRL_MEAS_REG, 0x25),
+        read(DATA_REG, data.to_vec()),
+    ].concat()
+}
+
+#[test]
+fn calibration_verified_at_startup() {
+    let mut expectations = calibration();
+    expectations.extend(verification([82, 79, 0, 128, 189, 0, 117, 97]));
+
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    assert_eq!(sensor.verify_calibration(&mut NoopDelay::new()), Ok(()));
+
+    drop(sensor);
+    i2c_clone.done();
+}
+
+#[test]
+fn self_test() {
+    let mut expectations = calibration();
+    expectations.extend(read(CHIP_ID_REG, vec![0x60]));
+    expectations.extend(verification([82, 79, 0, 128, 189, 0, 117, 97]));
+
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    assert_eq!(sensor.self_test(&mut NoopDelay::new()), Ok(()));
+
+    drop(sensor);
+    i2c_clone.done();
+}
+
+#[test]
+fn implausible_reading_flags_calibration() {
+    let mut expectations = calibration();
+    // Full scale temperature compensates to far above 85 degrees
+    expectations.extend(verification([82, 79, 0, 0xFF, 0xFF, 0xF0, 117, 97]));
+
+    expectations.extend(drop_sleep());
+    let i2c = I2cMock::new(&expectations);
+    let mut i2c_clone = i2c.clone();
+
+    let mut sensor = AtmosphericSensor::new(i2c, Address::Default).unwrap();
+    assert_eq!(sensor.verify_calibration(&mut NoopDelay::new()), Err(SensorError::CalibrationFailed));
+
+    drop(sensor);
+    i2c_clone.done();
+}